@@ -10,6 +10,16 @@ pub fn generate_bindings() -> Result<Bindings, Error> {
         .header_contents("wrapper.h", include_str!("wrapper.h"))
         .rust_target(bindgen::LATEST_STABLE_RUST)
         .whitelist_function("ftdi_.*")
+        .whitelist_function("libusb_ref_device")
+        .whitelist_function("libusb_unref_device")
+        .whitelist_function("libusb_get_device")
+        .whitelist_function("libusb_get_device_descriptor")
+        .whitelist_function("libusb_get_bus_number")
+        .whitelist_function("libusb_get_device_address")
+        .whitelist_function("libusb_open")
+        .whitelist_function("libusb_close")
+        .whitelist_function("libusb_bulk_transfer")
+        .whitelist_function("libusb_get_string_descriptor_ascii")
         .whitelist_type("ftdi_.*")
         .layout_tests(true)
         .derive_debug(true)
@@ -0,0 +1,140 @@
+use crate::ChipType;
+
+/// Base clock of the H-type chips when the divide-by-10 prescaler is used
+const H_CLK: u32 = 120_000_000;
+/// Base clock of the other chips
+const C_CLK: u32 = 48_000_000;
+
+/// Returns the baud rate the chip actually generates for the requested rate, or `None` for a
+/// zero rate.
+///
+/// This follows the divisor calculation of `ftdi_convert_baudrate` in libftdi1, which doesn't
+/// expose the result.
+pub(crate) fn actual_baud_rate(chip: ChipType, baud: u32) -> Option<u32> {
+    if baud == 0 {
+        return None;
+    }
+    let baud = u64::from(baud);
+    let actual = match chip {
+        ChipType::Ft2232H | ChipType::Ft4232H | ChipType::Ft232H
+            if baud * 10 > u64::from(H_CLK / 0x3fff) =>
+        {
+            clock_bits(baud, H_CLK, 10)
+        }
+        ChipType::Ft2232H
+        | ChipType::Ft4232H
+        | ChipType::Ft232H
+        | ChipType::Bm
+        | ChipType::Ft2232C
+        | ChipType::R
+        | ChipType::Ft230X => clock_bits(baud, C_CLK, 16),
+        // libftdi1 treats chip types it doesn't know as AM
+        ChipType::Am | ChipType::Unknown(_) => clock_bits_am(baud),
+    };
+    Some(actual as u32)
+}
+
+fn clock_bits(baud: u64, clk: u32, clk_div: u32) -> u64 {
+    let clk = u64::from(clk);
+    let clk_div = u64::from(clk_div);
+    if baud >= clk / clk_div {
+        clk / clk_div
+    } else if baud >= clk / (clk_div + clk_div / 2) {
+        clk / (clk_div + clk_div / 2)
+    } else if baud >= clk / (2 * clk_div) {
+        clk / (2 * clk_div)
+    } else {
+        // 3 fractional bits, and one more bit for rounding
+        let divisor = clk * 16 / clk_div / baud;
+        let mut divisor = divisor / 2 + (divisor & 1);
+        if divisor > 0x20000 {
+            divisor = 0x1ffff;
+        }
+        let actual = clk * 16 / clk_div / divisor;
+        actual / 2 + (actual & 1)
+    }
+}
+
+fn clock_bits_am(baud: u64) -> u64 {
+    const ADJUST_UP: [u64; 8] = [0, 0, 0, 1, 0, 3, 2, 1];
+    const ADJUST_DOWN: [u64; 8] = [0, 0, 0, 1, 0, 1, 2, 3];
+    let mut divisor = 24_000_000 / baud;
+    // Round down to a supported fraction
+    divisor -= ADJUST_DOWN[(divisor & 7) as usize];
+    let mut best = None;
+    // Division rounds down, so try the next divisor as well
+    for try_divisor in divisor..divisor + 2 {
+        let try_divisor = if try_divisor <= 8 {
+            8
+        } else if divisor < 16 {
+            // Divisors 9 through 15 are not supported
+            16
+        } else {
+            (try_divisor + ADJUST_UP[(try_divisor & 7) as usize]).min(0x1fff8)
+        };
+        let estimate = (24_000_000 + try_divisor / 2) / try_divisor;
+        let diff = estimate.abs_diff(baud);
+        match best {
+            Some((_, best_diff)) if best_diff <= diff => (),
+            _ => best = Some((estimate, diff)),
+        }
+    }
+    best.map_or(0, |(estimate, _)| estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Expected values from ftdi_convert_baudrate in libftdi1
+    const RATES: [(u32, u32, u32, u32); 16] = [
+        // requested, AM, BM, H
+        (1, 183, 183, 183),
+        (183, 183, 183, 183),
+        (300, 300, 300, 300),
+        (732, 732, 732, 732),
+        (733, 733, 733, 733),
+        (9600, 9600, 9600, 9600),
+        (19200, 19200, 19200, 19200),
+        (115_200, 115_385, 115_385, 115_246),
+        (230_400, 230_769, 230_769, 230_216),
+        (460_800, 461_538, 461_538, 461_538),
+        (921_600, 923_077, 923_077, 923_077),
+        (1_000_000, 1_000_000, 1_000_000, 1_000_000),
+        (2_000_000, 1_500_000, 2_000_000, 2_000_000),
+        (3_000_000, 3_000_000, 3_000_000, 3_000_000),
+        (6_000_000, 3_000_000, 3_000_000, 6_000_000),
+        (12_000_000, 3_000_000, 3_000_000, 12_000_000),
+    ];
+
+    #[test]
+    fn zero_rate() {
+        assert_eq!(actual_baud_rate(ChipType::Bm, 0), None);
+    }
+
+    #[test]
+    fn am_rates() {
+        for &(baud, am, _, _) in RATES.iter() {
+            assert_eq!(actual_baud_rate(ChipType::Am, baud), Some(am), "{}", baud);
+        }
+    }
+
+    #[test]
+    fn bm_rates() {
+        for &(baud, _, bm, _) in RATES.iter() {
+            assert_eq!(actual_baud_rate(ChipType::Bm, baud), Some(bm), "{}", baud);
+        }
+    }
+
+    #[test]
+    fn h_rates() {
+        for &(baud, _, _, h) in RATES.iter() {
+            assert_eq!(
+                actual_baud_rate(ChipType::Ft232H, baud),
+                Some(h),
+                "{}",
+                baud
+            );
+        }
+    }
+}
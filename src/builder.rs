@@ -0,0 +1,84 @@
+use crate::{BitMode, Context, ContextError, Device, FlowControl, FtdiError, Interface};
+
+/// Builder for opening a device and applying the usual settings in one go.
+///
+/// Settings are applied in this order: interface, bit mode, baud rate, latency timer, flow
+/// control. The bit mode goes before the baud rate, because libftdi1 applies its bit-bang
+/// baud rate multiplier when the baud rate is set. If any step fails, the device is closed and
+/// the error is returned.
+#[derive(Default)]
+#[must_use = "builder methods return a new builder instead of modifying it"]
+pub struct ContextBuilder {
+    interface: Option<Interface>,
+    baud_rate: Option<u32>,
+    latency_timer: Option<u8>,
+    bit_mode: Option<(u8, BitMode)>,
+    flow_control: Option<FlowControl>,
+}
+
+impl ContextBuilder {
+    pub fn new() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+    /// Selects the used chip interface
+    pub fn interface(mut self, interface: Interface) -> ContextBuilder {
+        self.interface = Some(interface);
+        self
+    }
+    /// Sets the chip baud rate
+    pub fn baud_rate(mut self, baud: u32) -> ContextBuilder {
+        self.baud_rate = Some(baud);
+        self
+    }
+    /// Sets the latency timer value (in milliseconds)
+    pub fn latency_timer(mut self, millis: u8) -> ContextBuilder {
+        self.latency_timer = Some(millis);
+        self
+    }
+    /// Sets the bit mode and pin mask
+    pub fn bit_mode(mut self, mask: u8, bit_mode: BitMode) -> ContextBuilder {
+        self.bit_mode = Some((mask, bit_mode));
+        self
+    }
+    /// Sets the flow control setting
+    pub fn flow_control(mut self, flow_control: FlowControl) -> ContextBuilder {
+        self.flow_control = Some(flow_control);
+        self
+    }
+    /// Opens the first FTDI device that has the given vendor and product id
+    pub fn open_vid_pid(self, vendor: u16, product: u16) -> Result<Device, FtdiError> {
+        self.open(|ctx| ctx.usb_open(vendor, product))
+    }
+    /// Opens the first FTDI device that has the given vendor id, product id and serial number
+    pub fn open_serial(self, vendor: u16, product: u16, serial: &str) -> Result<Device, FtdiError> {
+        self.open(|ctx| ctx.usb_open_desc(vendor, product, None, Some(serial)))
+    }
+    fn open<F>(self, open: F) -> Result<Device, FtdiError>
+    where
+        F: FnOnce(Context) -> Result<Device, ContextError>,
+    {
+        let mut ctx = Context::new()?;
+        if let Some(interface) = self.interface {
+            ctx.set_interface(interface)?;
+        }
+        // Dropping the device on failure closes it
+        let mut device = open(ctx)?;
+        self.configure(&mut device)?;
+        Ok(device)
+    }
+    fn configure(self, ctx: &mut Device) -> Result<(), FtdiError> {
+        if let Some((mask, bit_mode)) = self.bit_mode {
+            ctx.set_bit_mode(mask, bit_mode)?;
+        }
+        if let Some(baud) = self.baud_rate {
+            ctx.set_baud_rate(baud)?;
+        }
+        if let Some(millis) = self.latency_timer {
+            ctx.set_latency_timer(millis)?;
+        }
+        if let Some(flow_control) = self.flow_control {
+            ctx.set_flow_control(flow_control)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{Device, FtdiError};
+
+/// Size of the chunks `Bytes` reads at a time
+const CHUNK_SIZE: usize = 512;
+
+/// Iterator over the bytes read from a device, created by `Device::bytes`.
+///
+/// Data is read in chunks with `read_data`, so iterating doesn't cost a library call per byte.
+/// Iteration ends when a read returns no data, which happens once the chip has sent a
+/// status-only packet, i.e. after about one latency timer period without data (see
+/// `Device::set_latency_timer`). A USB read timeout is returned as `Some(Err(_))` instead.
+/// After an error, the next call to `next` tries to read again.
+#[derive(Debug)]
+pub struct Bytes<'a> {
+    ctx: &'a mut Device,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> Iterator for Bytes<'a> {
+    type Item = Result<u8, FtdiError>;
+    fn next(&mut self) -> Option<Result<u8, FtdiError>> {
+        if self.pos == self.len {
+            match self.ctx.read_data(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        let byte = self.buf[self.pos];
+        self.pos += 1;
+        Some(Ok(byte))
+    }
+}
+
+impl Device {
+    /// Returns an iterator over the bytes read from the device.
+    ///
+    /// Bytes read into the iterator's buffer but not consumed are lost when it's dropped.
+    ///
+    /// Note that on an owned `Device` with `io::Read` in scope, `device.bytes()` resolves to
+    /// `io::Read::bytes`. Call this as `Device::bytes(&mut device)` or `(&mut device).bytes()`
+    /// in that case.
+    pub fn bytes(&mut self) -> Bytes<'_> {
+        Bytes {
+            ctx: self,
+            buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{Bits, Device, FlowControl, FtdiError, Parity, StopBits};
+
+/// Snapshot of the settings of a device, for applying the same settings to another device.
+///
+/// libftdi1 doesn't keep track of the flow control and line property settings, so
+/// `Device::clone_config` leaves them as `None`. They can be filled in by hand, and are
+/// applied only if set.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+    /// Baud rate, or `None` if it hasn't been set
+    pub baud_rate: Option<u32>,
+    /// Latency timer value (in milliseconds)
+    pub latency_timer: u8,
+    /// Flow control setting
+    pub flow_control: Option<FlowControl>,
+    /// Data bits, stop bits and parity
+    pub line_property: Option<(Bits, StopBits, Parity)>,
+    /// USB read timeout (in milliseconds)
+    pub read_timeout: i32,
+    /// USB write timeout (in milliseconds)
+    pub write_timeout: i32,
+}
+
+impl Device {
+    /// Captures the current settings of the open device.
+    ///
+    /// The baud rate is the rate last requested with `set_baud_rate`.
+    pub fn clone_config(&mut self) -> Result<Config, FtdiError> {
+        let (mut baud_rate, bit_bang) = unsafe { ((*self.0).baudrate, (*self.0).bitbang_enabled) };
+        // libftdi1 stores the rate multiplied by 4 in bit-bang mode
+        if bit_bang != 0 {
+            baud_rate /= 4;
+        }
+        Ok(Config {
+            baud_rate: if baud_rate > 0 {
+                Some(baud_rate as u32)
+            } else {
+                None
+            },
+            latency_timer: self.get_latency_timer()?,
+            flow_control: None,
+            line_property: None,
+            read_timeout: self.read_timeout(),
+            write_timeout: self.write_timeout(),
+        })
+    }
+    /// Applies settings captured with `clone_config` to the open device.
+    ///
+    /// Settings are applied in this order: baud rate, line properties, flow control, latency
+    /// timer, timeouts. Stops at the first error.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), FtdiError> {
+        if let Some(baud_rate) = config.baud_rate {
+            self.set_baud_rate(baud_rate)?;
+        }
+        if let Some((bits, stop, parity)) = config.line_property {
+            self.set_line_property(bits, stop, parity)?;
+        }
+        if let Some(flow_control) = config.flow_control {
+            self.set_flow_control(flow_control)?;
+        }
+        self.set_latency_timer(config.latency_timer)?;
+        self.set_read_timeout(config.read_timeout);
+        self.set_write_timeout(config.write_timeout);
+        Ok(())
+    }
+}
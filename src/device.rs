@@ -0,0 +1,273 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::{string_descriptor, sys, Context, FtdiError, RawContext};
+
+/// USB devices found by `Context::find_all`.
+///
+/// The underlying libftdi1 device list is freed when the list, or an iterator created from it,
+/// is dropped. The list borrows the `Context` that enumerated it, so the context can't be
+/// opened while the list is alive. The `DeviceInfo`s taken from the list don't borrow the
+/// context, so drop the list before opening one of them with `Context::usb_open_dev`.
+pub struct DeviceList<'ctx> {
+    head: *mut sys::ftdi_device_list,
+    ctx: Arc<RawContext>,
+    _ctx: PhantomData<&'ctx Context>,
+}
+
+impl<'ctx> DeviceList<'ctx> {
+    pub(crate) unsafe fn new(
+        head: *mut sys::ftdi_device_list,
+        ctx: Arc<RawContext>,
+    ) -> DeviceList<'ctx> {
+        DeviceList {
+            head,
+            ctx,
+            _ctx: PhantomData,
+        }
+    }
+    /// Returns an iterator over the devices
+    pub fn iter(&self) -> DeviceIter<'_, 'ctx> {
+        DeviceIter {
+            node: self.head,
+            list: self,
+        }
+    }
+    /// Returns the number of devices
+    pub fn len(&self) -> usize {
+        self.iter_nodes().count()
+    }
+    /// Returns true if no devices were found
+    pub fn is_empty(&self) -> bool {
+        self.head.is_null()
+    }
+    fn iter_nodes(&self) -> impl Iterator<Item = *mut sys::ftdi_device_list> {
+        let mut node = self.head;
+        std::iter::from_fn(move || {
+            if node.is_null() {
+                None
+            } else {
+                let current = node;
+                node = unsafe { (*node).next };
+                Some(current)
+            }
+        })
+    }
+}
+
+impl<'ctx> fmt::Debug for DeviceList<'ctx> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'ctx> Drop for DeviceList<'ctx> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::ftdi_list_free(&mut self.head);
+        }
+    }
+}
+
+impl<'ctx> IntoIterator for DeviceList<'ctx> {
+    type Item = DeviceInfo;
+    type IntoIter = DeviceIntoIter<'ctx>;
+    fn into_iter(self) -> DeviceIntoIter<'ctx> {
+        DeviceIntoIter {
+            node: self.head,
+            list: self,
+        }
+    }
+}
+
+impl<'a, 'ctx> IntoIterator for &'a DeviceList<'ctx> {
+    type Item = DeviceInfo;
+    type IntoIter = DeviceIter<'a, 'ctx>;
+    fn into_iter(self) -> DeviceIter<'a, 'ctx> {
+        self.iter()
+    }
+}
+
+/// Owning iterator over a `DeviceList`
+pub struct DeviceIntoIter<'ctx> {
+    node: *mut sys::ftdi_device_list,
+    // Keeps the libftdi1 list alive until the iterator is dropped
+    list: DeviceList<'ctx>,
+}
+
+impl<'ctx> Iterator for DeviceIntoIter<'ctx> {
+    type Item = DeviceInfo;
+    fn next(&mut self) -> Option<DeviceInfo> {
+        next_device(&mut self.node, &self.list.ctx)
+    }
+}
+
+impl<'ctx> fmt::Debug for DeviceIntoIter<'ctx> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeviceIntoIter")
+            .field("list", &self.list)
+            .finish()
+    }
+}
+
+/// Borrowing iterator over a `DeviceList`
+pub struct DeviceIter<'a, 'ctx> {
+    node: *mut sys::ftdi_device_list,
+    list: &'a DeviceList<'ctx>,
+}
+
+impl<'a, 'ctx> Iterator for DeviceIter<'a, 'ctx> {
+    type Item = DeviceInfo;
+    fn next(&mut self) -> Option<DeviceInfo> {
+        next_device(&mut self.node, &self.list.ctx)
+    }
+}
+
+fn next_device(node: &mut *mut sys::ftdi_device_list, ctx: &Arc<RawContext>) -> Option<DeviceInfo> {
+    if node.is_null() {
+        None
+    } else {
+        unsafe {
+            let device = DeviceInfo::new((**node).dev, ctx);
+            *node = (**node).next;
+            Some(device)
+        }
+    }
+}
+
+enum UsbString {
+    Manufacturer,
+    Description,
+    Serial,
+}
+
+/// A USB device found by `Context::find_all`.
+///
+/// Holds a reference to the underlying libusb device, so the handle stays valid after the
+/// `DeviceList` has been dropped. The libusb device belongs to the libusb session of the
+/// `Context` that enumerated it, which is kept alive by the `DeviceInfo` instead of borrowed,
+/// so the device can be passed to `Context::usb_open_dev`.
+///
+/// Equality and hashing only consider the bus number and device address, which identify a
+/// device for as long as it stays connected. The device strings are not compared, because
+/// reading them requires USB I/O.
+pub struct DeviceInfo {
+    bus_number: u8,
+    device_address: u8,
+    dev: *mut sys::libusb_device,
+    ctx: Arc<RawContext>,
+}
+
+impl DeviceInfo {
+    unsafe fn new(dev: *mut sys::libusb_device, ctx: &Arc<RawContext>) -> DeviceInfo {
+        DeviceInfo {
+            bus_number: sys::libusb_get_bus_number(dev),
+            device_address: sys::libusb_get_device_address(dev),
+            dev: sys::libusb_ref_device(dev),
+            ctx: Arc::clone(ctx),
+        }
+    }
+    /// Returns the number of the USB bus the device is connected to
+    pub fn bus_number(&self) -> u8 {
+        self.bus_number
+    }
+    /// Returns the address of the device on its USB bus
+    pub fn device_address(&self) -> u8 {
+        self.device_address
+    }
+    /// Reads the manufacturer string from the device.
+    ///
+    /// The device strings are read by opening the device separately, so this can be called
+    /// while a device is open, even the same one.
+    pub fn manufacturer(&self) -> Result<String, FtdiError> {
+        self.usb_string(UsbString::Manufacturer)
+    }
+    /// Reads the product description string from the device
+    pub fn description(&self) -> Result<String, FtdiError> {
+        self.usb_string(UsbString::Description)
+    }
+    /// Reads the serial number string from the device
+    pub fn serial(&self) -> Result<String, FtdiError> {
+        self.usb_string(UsbString::Serial)
+    }
+    pub fn raw(&self) -> *mut sys::libusb_device {
+        self.dev
+    }
+    /// Returns true if the device was found by the given raw context
+    pub(crate) fn belongs_to(&self, ctx: *mut sys::ftdi_context) -> bool {
+        self.ctx.0 == ctx
+    }
+    fn usb_string(&self, string: UsbString) -> Result<String, FtdiError> {
+        let mut desc = MaybeUninit::uninit();
+        let desc = match unsafe { sys::libusb_get_device_descriptor(self.dev, desc.as_mut_ptr()) } {
+            code if code < 0 => {
+                return Err(FtdiError::Other(
+                    code,
+                    "failed to get USB device descriptor",
+                ))
+            }
+            _ => unsafe { desc.assume_init() },
+        };
+        let (index, missing) = match string {
+            UsbString::Manufacturer => (desc.iManufacturer, "device has no manufacturer string"),
+            UsbString::Description => (desc.iProduct, "device has no product string"),
+            UsbString::Serial => (desc.iSerialNumber, "device has no serial number string"),
+        };
+        // libftdi1 would read the strings using the context's device handle, and close it
+        // afterwards, so a separate handle is used instead
+        let mut handle = ptr::null_mut();
+        match unsafe { sys::libusb_open(self.dev, &mut handle) } {
+            // LIBUSB_ERROR_ACCESS
+            -3 => return Err(FtdiError::AccessDenied),
+            // LIBUSB_ERROR_NO_DEVICE
+            -4 => return Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => return Err(FtdiError::Other(code, "failed to open USB device")),
+            _ => (),
+        }
+        let result = string_descriptor(handle, index, missing);
+        unsafe { sys::libusb_close(handle) };
+        result
+    }
+}
+
+impl Clone for DeviceInfo {
+    fn clone(&self) -> DeviceInfo {
+        unsafe { DeviceInfo::new(self.dev, &self.ctx) }
+    }
+}
+
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &DeviceInfo) -> bool {
+        self.bus_number == other.bus_number && self.device_address == other.device_address
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl Hash for DeviceInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bus_number.hash(state);
+        self.device_address.hash(state);
+    }
+}
+
+impl fmt::Debug for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeviceInfo")
+            .field("bus_number", &self.bus_number)
+            .field("device_address", &self.device_address)
+            .finish()
+    }
+}
+
+impl Drop for DeviceInfo {
+    fn drop(&mut self) {
+        unsafe {
+            sys::libusb_unref_device(self.dev);
+        }
+    }
+}
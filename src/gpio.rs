@@ -0,0 +1,69 @@
+use crate::{BitMode, Device, FtdiError};
+
+/// GPIO access to the 8 pins of an interface in asynchronous bit-bang mode.
+///
+/// A direction bit set to 1 makes the pin an output.
+///
+/// The last directions and output values sent to the chip are cached, and setting the same
+/// ones again doesn't cause any USB traffic. After changing the pins through `context_mut`,
+/// call `force_sync` to bring the chip back in line with the cache.
+#[derive(Debug)]
+pub struct GpioPort {
+    ctx: Device,
+    direction: u8,
+    value: Option<u8>,
+}
+
+impl GpioPort {
+    /// Switches a device to bit-bang mode with the given pin directions
+    pub fn new(mut ctx: Device, direction: u8) -> Result<GpioPort, FtdiError> {
+        ctx.set_bit_mode(direction, BitMode::BitBang)?;
+        Ok(GpioPort {
+            ctx,
+            direction,
+            value: None,
+        })
+    }
+    /// Returns the current pin directions
+    pub fn direction(&self) -> u8 {
+        self.direction
+    }
+    /// Sets the pin directions, unless they are unchanged
+    pub fn set_direction(&mut self, direction: u8) -> Result<(), FtdiError> {
+        if direction != self.direction {
+            self.ctx.set_bit_mode(direction, BitMode::BitBang)?;
+            self.direction = direction;
+        }
+        Ok(())
+    }
+    /// Sets the values of the output pins, unless they are unchanged.
+    ///
+    /// Bits of input pins are ignored.
+    pub fn write(&mut self, value: u8) -> Result<(), FtdiError> {
+        if self.value != Some(value) {
+            self.ctx.write_all(&[value])?;
+            self.value = Some(value);
+        }
+        Ok(())
+    }
+    /// Reads the current state of all pins
+    pub fn read(&mut self) -> Result<u8, FtdiError> {
+        self.ctx.read_pins()
+    }
+    /// Sends the cached directions and output values to the chip, bypassing the cache
+    pub fn force_sync(&mut self) -> Result<(), FtdiError> {
+        self.ctx.set_bit_mode(self.direction, BitMode::BitBang)?;
+        if let Some(value) = self.value {
+            self.ctx.write_all(&[value])?;
+        }
+        Ok(())
+    }
+    /// Returns the underlying device for raw access
+    pub fn context_mut(&mut self) -> &mut Device {
+        &mut self.ctx
+    }
+    /// Returns the underlying device
+    pub fn into_inner(self) -> Device {
+        self.ctx
+    }
+}
@@ -1,17 +1,65 @@
 pub extern crate gekkio_ftdi_sys as sys;
 
+mod baud;
+mod builder;
+mod bytes;
+mod config;
+mod device;
+mod gpio;
+mod modem;
+mod mpsse;
+#[cfg(feature = "serialport")]
+mod serial;
+#[cfg(feature = "embedded-hal")]
+mod spi;
+
+pub use crate::builder::ContextBuilder;
+pub use crate::bytes::Bytes;
+pub use crate::config::Config;
+pub use crate::device::{DeviceInfo, DeviceIntoIter, DeviceIter, DeviceList};
+pub use crate::gpio::GpioPort;
+pub use crate::modem::{ModemChange, ModemMonitor};
+pub use crate::mpsse::MpsseBuilder;
+#[cfg(feature = "serialport")]
+pub use crate::serial::FtdiSerialPort;
+#[cfg(feature = "embedded-hal")]
+pub use crate::spi::MpsseSpi;
+
 use bitflags::bitflags;
-use std::borrow::BorrowMut;
+use std::any::Any;
+use std::convert::TryFrom;
 use std::error::Error;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fmt;
-use std::mem;
-use std::os::raw::c_int;
+use std::io;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
 use std::str;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum FtdiError {
     UsbDeviceUnavailable,
+    /// No matching USB device was found
+    DeviceNotFound,
+    /// Insufficient permissions to open the USB device
+    AccessDenied,
+    /// The USB interface couldn't be claimed, e.g. because a kernel driver is using it
+    ClaimInterfaceFailed,
+    /// The baud rate is zero or can't be generated by the chip
+    InvalidBaudRate,
+    /// The operation didn't complete before its deadline
+    Timeout,
+    /// A USB control request to the chip failed, e.g. because of a stall or a USB error
+    ControlTransferFailed(&'static str),
+    InvalidArgument(&'static str),
     Other(i32, &'static str),
 }
 
@@ -19,13 +67,90 @@ impl fmt::Display for FtdiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             FtdiError::UsbDeviceUnavailable => write!(f, "USB device unavailable"),
+            FtdiError::DeviceNotFound => write!(f, "USB device not found"),
+            FtdiError::AccessDenied => write!(f, "insufficient permissions to open USB device"),
+            FtdiError::ClaimInterfaceFailed => write!(f, "failed to claim USB interface"),
+            FtdiError::InvalidBaudRate => write!(f, "invalid baud rate"),
+            FtdiError::Timeout => write!(f, "operation timed out"),
+            FtdiError::ControlTransferFailed(msg) => {
+                write!(f, "USB control transfer failed: {}", msg)
+            }
+            FtdiError::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
             FtdiError::Other(code, msg) => write!(f, "libftdi1 error code {}: {}", code, msg),
         }
     }
 }
 
+impl FtdiError {
+    /// Code reported by `code` for `FtdiError::UsbDeviceUnavailable`.
+    ///
+    /// libftdi1 uses several codes for an unavailable device depending on the function, so this
+    /// is the most common one (-2) instead of the original code.
+    pub const USB_DEVICE_UNAVAILABLE_CODE: i32 = -2;
+
+    /// Returns the libftdi1 error code, if the error has one.
+    ///
+    /// Returns the original code for `FtdiError::Other`, and the code libftdi1 reports the
+    /// error with for the named variants. libftdi1 uses several codes for an unavailable
+    /// device, so `FtdiError::UsbDeviceUnavailable` reports `USB_DEVICE_UNAVAILABLE_CODE`.
+    /// `FtdiError::Timeout` reports `LIBUSB_ERROR_TIMEOUT` (-7), which libftdi1 passes through
+    /// from USB transfers. Returns `None` for `FtdiError::InvalidArgument`, which is detected
+    /// by this crate before calling libftdi1.
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            FtdiError::UsbDeviceUnavailable => Some(FtdiError::USB_DEVICE_UNAVAILABLE_CODE),
+            FtdiError::DeviceNotFound => Some(-3),
+            FtdiError::AccessDenied => Some(-8),
+            FtdiError::ClaimInterfaceFailed => Some(-5),
+            FtdiError::InvalidBaudRate | FtdiError::ControlTransferFailed(_) => Some(-1),
+            FtdiError::Timeout => Some(-7),
+            FtdiError::InvalidArgument(_) => None,
+            FtdiError::Other(code, _) => Some(*code),
+        }
+    }
+    /// Returns true if the error is `FtdiError::UsbDeviceUnavailable`.
+    ///
+    /// The device may have been unplugged or reset, so the operation may succeed after
+    /// reopening it, e.g. with `Device::reopen`.
+    pub fn is_device_unavailable(&self) -> bool {
+        matches!(self, FtdiError::UsbDeviceUnavailable)
+    }
+}
+
 impl Error for FtdiError {}
 
+impl From<FtdiError> for io::Error {
+    fn from(err: FtdiError) -> io::Error {
+        let kind = match err {
+            FtdiError::UsbDeviceUnavailable => io::ErrorKind::NotConnected,
+            FtdiError::DeviceNotFound => io::ErrorKind::NotFound,
+            FtdiError::AccessDenied => io::ErrorKind::PermissionDenied,
+            FtdiError::InvalidArgument(_) | FtdiError::InvalidBaudRate => {
+                io::ErrorKind::InvalidInput
+            }
+            // -7 is LIBUSB_ERROR_TIMEOUT, which libftdi1 passes through from USB transfers
+            FtdiError::Timeout | FtdiError::Other(-7, _) => io::ErrorKind::TimedOut,
+            FtdiError::ClaimInterfaceFailed | FtdiError::ControlTransferFailed(_) => {
+                io::ErrorKind::Other
+            }
+            FtdiError::Other(..) => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Error for converting an integer that doesn't correspond to any enum variant
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct UnknownValue(pub u32);
+
+impl fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown value: {}", self.0)
+    }
+}
+
+impl Error for UnknownValue {}
+
 bitflags! {
     #[repr(transparent)]
     pub struct ModemStatus: u16 {
@@ -56,7 +181,78 @@ bitflags! {
     }
 }
 
+impl ModemStatus {
+    /// Returns true if received data is ready
+    pub fn is_data_ready(self) -> bool {
+        self.contains(ModemStatus::DR)
+    }
+    /// Returns true if Clear To Send (CTS) is active
+    pub fn cts(self) -> bool {
+        self.contains(ModemStatus::CTS)
+    }
+    /// Returns true if Data Set Ready (DSR) is active
+    pub fn dsr(self) -> bool {
+        self.contains(ModemStatus::DSR)
+    }
+    /// Returns true if Data Carrier Detect (DCD) is active
+    pub fn dcd(self) -> bool {
+        self.contains(ModemStatus::DCD)
+    }
+    /// Returns true if Ring Indicator (RI) is active
+    pub fn ring_indicator(self) -> bool {
+        self.contains(ModemStatus::RI)
+    }
+    /// Returns true if a framing error has occurred
+    pub fn has_framing_error(self) -> bool {
+        self.contains(ModemStatus::FE)
+    }
+    /// Returns true if a parity error has occurred
+    pub fn has_parity_error(self) -> bool {
+        self.contains(ModemStatus::PE)
+    }
+    /// Returns true if an overrun error has occurred
+    pub fn has_overrun(self) -> bool {
+        self.contains(ModemStatus::OE)
+    }
+}
+
+/// State of the 8 pins of a bit-bang or MPSSE interface, as returned by `read_pins`.
+///
+/// Bit `n` is the state of pin `n` (D0-D7 in bit-bang mode, ADBUS0-7 in MPSSE mode).
+#[derive(Copy, Clone, Default, Eq, Hash, PartialEq)]
+pub struct Pins(pub u8);
+
+impl Pins {
+    /// Returns true if pin `n` (0-7) is high
+    pub fn pin(self, n: u8) -> bool {
+        assert!(n < 8, "pin index out of range: {}", n);
+        self.0 & (1 << n) != 0
+    }
+    /// Returns the raw pin states
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl ops::Index<u8> for Pins {
+    type Output = bool;
+    fn index(&self, n: u8) -> &bool {
+        if self.pin(n) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl fmt::Debug for Pins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pins({:#010b})", self.0)
+    }
+}
+
 #[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum FlowControl {
     None = 0x000,
     RtsCts = 0x100,
@@ -73,6 +269,104 @@ pub enum Interface {
     D = sys::ftdi_interface_INTERFACE_D,
 }
 
+impl TryFrom<u32> for Interface {
+    type Error = UnknownValue;
+    fn try_from(value: u32) -> Result<Interface, UnknownValue> {
+        match value {
+            sys::ftdi_interface_INTERFACE_A => Ok(Interface::A),
+            sys::ftdi_interface_INTERFACE_B => Ok(Interface::B),
+            sys::ftdi_interface_INTERFACE_C => Ok(Interface::C),
+            sys::ftdi_interface_INTERFACE_D => Ok(Interface::D),
+            _ => Err(UnknownValue(value)),
+        }
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Interface::A => write!(f, "Interface A"),
+            Interface::B => write!(f, "Interface B"),
+            Interface::C => write!(f, "Interface C"),
+            Interface::D => write!(f, "Interface D"),
+        }
+    }
+}
+
+/// FTDI chip type detected by libftdi1
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChipType {
+    Am,
+    Bm,
+    Ft2232C,
+    R,
+    Ft2232H,
+    Ft4232H,
+    Ft232H,
+    Ft230X,
+    /// A chip type added in a newer libftdi1 version, with its raw `ftdi_chip_type` value
+    Unknown(u32),
+}
+
+impl ChipType {
+    fn from_raw(value: sys::ftdi_chip_type) -> ChipType {
+        match value {
+            sys::ftdi_chip_type_TYPE_AM => ChipType::Am,
+            sys::ftdi_chip_type_TYPE_BM => ChipType::Bm,
+            sys::ftdi_chip_type_TYPE_2232C => ChipType::Ft2232C,
+            sys::ftdi_chip_type_TYPE_R => ChipType::R,
+            sys::ftdi_chip_type_TYPE_2232H => ChipType::Ft2232H,
+            sys::ftdi_chip_type_TYPE_4232H => ChipType::Ft4232H,
+            sys::ftdi_chip_type_TYPE_232H => ChipType::Ft232H,
+            sys::ftdi_chip_type_TYPE_230X => ChipType::Ft230X,
+            value => ChipType::Unknown(value),
+        }
+    }
+    /// Returns true for the high-speed H-type chips (FT2232H, FT4232H, FT232H)
+    pub fn is_high_speed(self) -> bool {
+        matches!(
+            self,
+            ChipType::Ft2232H | ChipType::Ft4232H | ChipType::Ft232H
+        )
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ModuleDetachMode {
+    /// Detach the kernel driver when opening a device
+    AutoDetach = sys::ftdi_module_detach_mode_AUTO_DETACH_SIO_MODULE,
+    /// Leave the kernel driver attached
+    DontDetach = sys::ftdi_module_detach_mode_DONT_DETACH_SIO_MODULE,
+    /// Detach the kernel driver when opening a device, and reattach it when closing
+    AutoDetachReattach = sys::ftdi_module_detach_mode_AUTO_DETACH_REATACH_SIO_MODULE,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bits {
+    Seven = sys::ftdi_bits_type_BITS_7,
+    Eight = sys::ftdi_bits_type_BITS_8,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StopBits {
+    One = sys::ftdi_stopbits_type_STOP_BIT_1,
+    OneHalf = sys::ftdi_stopbits_type_STOP_BIT_15,
+    Two = sys::ftdi_stopbits_type_STOP_BIT_2,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Parity {
+    None = sys::ftdi_parity_type_NONE,
+    Odd = sys::ftdi_parity_type_ODD,
+    Even = sys::ftdi_parity_type_EVEN,
+    Mark = sys::ftdi_parity_type_MARK,
+    Space = sys::ftdi_parity_type_SPACE,
+}
+
 #[repr(u32)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum BitMode {
@@ -87,6 +381,186 @@ pub enum BitMode {
     Ft1284 = sys::ftdi_mpsse_mode_BITMODE_FT1284,
 }
 
+impl BitMode {
+    fn from_raw(value: u32) -> Option<BitMode> {
+        match value {
+            sys::ftdi_mpsse_mode_BITMODE_RESET => Some(BitMode::Reset),
+            sys::ftdi_mpsse_mode_BITMODE_BITBANG => Some(BitMode::BitBang),
+            sys::ftdi_mpsse_mode_BITMODE_MPSSE => Some(BitMode::Mpsse),
+            sys::ftdi_mpsse_mode_BITMODE_SYNCBB => Some(BitMode::SyncBitBang),
+            sys::ftdi_mpsse_mode_BITMODE_MCU => Some(BitMode::Mcu),
+            sys::ftdi_mpsse_mode_BITMODE_OPTO => Some(BitMode::Opto),
+            sys::ftdi_mpsse_mode_BITMODE_CBUS => Some(BitMode::Cbus),
+            sys::ftdi_mpsse_mode_BITMODE_SYNCFF => Some(BitMode::SyncFf),
+            sys::ftdi_mpsse_mode_BITMODE_FT1284 => Some(BitMode::Ft1284),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u32> for BitMode {
+    type Error = UnknownValue;
+    fn try_from(value: u32) -> Result<BitMode, UnknownValue> {
+        BitMode::from_raw(value).ok_or(UnknownValue(value))
+    }
+}
+
+impl fmt::Display for BitMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BitMode::Reset => "reset",
+            BitMode::BitBang => "bit-bang",
+            BitMode::Mpsse => "MPSSE",
+            BitMode::SyncBitBang => "synchronous bit-bang",
+            BitMode::Mcu => "MCU host bus emulation",
+            BitMode::Opto => "fast opto-isolated serial",
+            BitMode::Cbus => "CBUS bit-bang",
+            BitMode::SyncFf => "synchronous FIFO",
+            BitMode::Ft1284 => "FT1284",
+        };
+        f.write_str(name)
+    }
+}
+
+/// EEPROM setting accessible with `get_eeprom_value` and `set_eeprom_value`
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EepromValue {
+    VendorId = sys::ftdi_eeprom_value_VENDOR_ID,
+    ProductId = sys::ftdi_eeprom_value_PRODUCT_ID,
+    SelfPowered = sys::ftdi_eeprom_value_SELF_POWERED,
+    RemoteWakeup = sys::ftdi_eeprom_value_REMOTE_WAKEUP,
+    IsNotPnp = sys::ftdi_eeprom_value_IS_NOT_PNP,
+    SuspendDbus7 = sys::ftdi_eeprom_value_SUSPEND_DBUS7,
+    InIsIsochronous = sys::ftdi_eeprom_value_IN_IS_ISOCHRONOUS,
+    OutIsIsochronous = sys::ftdi_eeprom_value_OUT_IS_ISOCHRONOUS,
+    SuspendPullDowns = sys::ftdi_eeprom_value_SUSPEND_PULL_DOWNS,
+    UseSerial = sys::ftdi_eeprom_value_USE_SERIAL,
+    UsbVersion = sys::ftdi_eeprom_value_USB_VERSION,
+    UseUsbVersion = sys::ftdi_eeprom_value_USE_USB_VERSION,
+    MaxPower = sys::ftdi_eeprom_value_MAX_POWER,
+    ChannelAType = sys::ftdi_eeprom_value_CHANNEL_A_TYPE,
+    ChannelBType = sys::ftdi_eeprom_value_CHANNEL_B_TYPE,
+    ChannelADriver = sys::ftdi_eeprom_value_CHANNEL_A_DRIVER,
+    ChannelBDriver = sys::ftdi_eeprom_value_CHANNEL_B_DRIVER,
+    Cbus0 = sys::ftdi_eeprom_value_CBUS_FUNCTION_0,
+    Cbus1 = sys::ftdi_eeprom_value_CBUS_FUNCTION_1,
+    Cbus2 = sys::ftdi_eeprom_value_CBUS_FUNCTION_2,
+    Cbus3 = sys::ftdi_eeprom_value_CBUS_FUNCTION_3,
+    Cbus4 = sys::ftdi_eeprom_value_CBUS_FUNCTION_4,
+    Cbus5 = sys::ftdi_eeprom_value_CBUS_FUNCTION_5,
+    Cbus6 = sys::ftdi_eeprom_value_CBUS_FUNCTION_6,
+    Cbus7 = sys::ftdi_eeprom_value_CBUS_FUNCTION_7,
+    Cbus8 = sys::ftdi_eeprom_value_CBUS_FUNCTION_8,
+    Cbus9 = sys::ftdi_eeprom_value_CBUS_FUNCTION_9,
+    HighCurrent = sys::ftdi_eeprom_value_HIGH_CURRENT,
+    HighCurrentA = sys::ftdi_eeprom_value_HIGH_CURRENT_A,
+    HighCurrentB = sys::ftdi_eeprom_value_HIGH_CURRENT_B,
+    Invert = sys::ftdi_eeprom_value_INVERT,
+    Group0Drive = sys::ftdi_eeprom_value_GROUP0_DRIVE,
+    Group0Schmitt = sys::ftdi_eeprom_value_GROUP0_SCHMITT,
+    Group0Slew = sys::ftdi_eeprom_value_GROUP0_SLEW,
+    Group1Drive = sys::ftdi_eeprom_value_GROUP1_DRIVE,
+    Group1Schmitt = sys::ftdi_eeprom_value_GROUP1_SCHMITT,
+    Group1Slew = sys::ftdi_eeprom_value_GROUP1_SLEW,
+    Group2Drive = sys::ftdi_eeprom_value_GROUP2_DRIVE,
+    Group2Schmitt = sys::ftdi_eeprom_value_GROUP2_SCHMITT,
+    Group2Slew = sys::ftdi_eeprom_value_GROUP2_SLEW,
+    Group3Drive = sys::ftdi_eeprom_value_GROUP3_DRIVE,
+    Group3Schmitt = sys::ftdi_eeprom_value_GROUP3_SCHMITT,
+    Group3Slew = sys::ftdi_eeprom_value_GROUP3_SLEW,
+    ChipSize = sys::ftdi_eeprom_value_CHIP_SIZE,
+    ChipType = sys::ftdi_eeprom_value_CHIP_TYPE,
+    PowerSave = sys::ftdi_eeprom_value_POWER_SAVE,
+    ClockPolarity = sys::ftdi_eeprom_value_CLOCK_POLARITY,
+    DataOrder = sys::ftdi_eeprom_value_DATA_ORDER,
+    FlowControl = sys::ftdi_eeprom_value_FLOW_CONTROL,
+    ChannelCDriver = sys::ftdi_eeprom_value_CHANNEL_C_DRIVER,
+    ChannelDDriver = sys::ftdi_eeprom_value_CHANNEL_D_DRIVER,
+    ChannelARs485 = sys::ftdi_eeprom_value_CHANNEL_A_RS485,
+    ChannelBRs485 = sys::ftdi_eeprom_value_CHANNEL_B_RS485,
+    ChannelCRs485 = sys::ftdi_eeprom_value_CHANNEL_C_RS485,
+    ChannelDRs485 = sys::ftdi_eeprom_value_CHANNEL_D_RS485,
+    ReleaseNumber = sys::ftdi_eeprom_value_RELEASE_NUMBER,
+    ExternalOscillator = sys::ftdi_eeprom_value_EXTERNAL_OSCILLATOR,
+    UserDataAddr = sys::ftdi_eeprom_value_USER_DATA_ADDR,
+}
+
+/// libftdi1 library version information
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Version {
+    pub major: i32,
+    pub minor: i32,
+    pub micro: i32,
+    pub version_str: String,
+    pub snapshot_str: String,
+}
+
+/// Manufacturer, product and serial number strings of a device
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeviceStrings {
+    pub manufacturer: String,
+    pub product: String,
+    pub serial: String,
+}
+
+/// EEPROM checksum stored in the image, and the one computed from the image contents
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EepromChecksum {
+    pub stored: u16,
+    pub computed: u16,
+}
+
+impl EepromChecksum {
+    /// Returns true if the stored checksum matches the computed one
+    pub fn is_valid(&self) -> bool {
+        self.stored == self.computed
+    }
+}
+
+/// Computes the checksum of an EEPROM image the same way as libftdi1 and the chip.
+///
+/// The checksum covers all words except the last one, which holds the checksum itself.
+fn compute_eeprom_checksum(chip_type: ChipType, image: &[u8]) -> u16 {
+    let words = image.len() / 2;
+    let mut checksum: u16 = 0xaaaa;
+    let mut i = 0;
+    while i + 1 < words {
+        // The FT-X user area in words 0x12-0x3f isn't covered by the checksum
+        if chip_type == ChipType::Ft230X && i == 0x12 {
+            i = 0x40;
+            continue;
+        }
+        checksum ^= u16::from_le_bytes([image[i * 2], image[i * 2 + 1]]);
+        checksum = checksum.rotate_left(1);
+        i += 1;
+    }
+    checksum
+}
+
+fn owned_string(value: *const c_char) -> String {
+    if value.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(value).to_string_lossy().into_owned() }
+    }
+}
+
+fn buf_string(buf: &[c_char]) -> String {
+    owned_string(buf.as_ptr())
+}
+
+/// Maps the error codes of `ftdi_usb_open_dev`, which the other `ftdi_usb_open*` functions pass
+/// through once they have found a matching device
+fn open_error(ctx: *mut sys::ftdi_context, code: c_int) -> FtdiError {
+    match code {
+        -5 => FtdiError::ClaimInterfaceFailed,
+        // Detaching the kernel driver failed with EPERM
+        -8 => FtdiError::AccessDenied,
+        _ => FtdiError::Other(code, error_msg(ctx)),
+    }
+}
+
 fn error_msg(ctx: *mut sys::ftdi_context) -> &'static str {
     unsafe {
         let msg = sys::ftdi_get_error_string(ctx);
@@ -98,155 +572,1149 @@ fn error_msg(ctx: *mut sys::ftdi_context) -> &'static str {
     }
 }
 
-pub struct Context(Box<sys::ftdi_context>);
+fn to_c_string(value: Option<&str>) -> Result<Option<CString>, FtdiError> {
+    value
+        .map(CString::new)
+        .transpose()
+        .map_err(|_| FtdiError::InvalidArgument("string contains a nul byte"))
+}
 
-impl Context {
-    /// Creates and initializes a new FTDI context
-    pub fn new() -> Result<Context, FtdiError> {
+fn c_string_ptr(value: &Option<CString>) -> *const c_char {
+    value.as_ref().map_or(ptr::null(), |value| value.as_ptr())
+}
+
+/// Reads the string descriptor `index` of an open device as ASCII.
+///
+/// Index 0 means that the device doesn't have the string, which fails with `missing` as the
+/// error message.
+fn string_descriptor(
+    usb_dev: *mut sys::libusb_device_handle,
+    index: u8,
+    missing: &'static str,
+) -> Result<String, FtdiError> {
+    if index == 0 {
+        return Err(FtdiError::Other(-1, missing));
+    }
+    let mut buf = [0; 128];
+    match unsafe {
+        sys::libusb_get_string_descriptor_ascii(
+            usb_dev,
+            index,
+            buf.as_mut_ptr(),
+            buf.len() as c_int,
+        )
+    } {
+        // LIBUSB_ERROR_NO_DEVICE
+        -4 => Err(FtdiError::UsbDeviceUnavailable),
+        code if code < 0 => Err(FtdiError::Other(
+            code,
+            "failed to read USB string descriptor",
+        )),
+        len => Ok(String::from_utf8_lossy(&buf[..len as usize]).into_owned()),
+    }
+}
+
+fn check_latency_timer(millis: u8) -> Result<(), FtdiError> {
+    if millis == 0 {
+        Err(FtdiError::InvalidArgument(
+            "latency timer must be between 1 and 255 milliseconds",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// An asynchronous USB transfer.
+///
+/// The transfer borrows its data buffer and context until it is finished with `wait`. Dropping
+/// an unfinished transfer cancels it. Leaking a transfer is not allowed, which is why
+/// `Device::submit_read` and `Device::submit_write` are unsafe.
+#[must_use = "dropping a transfer cancels it"]
+pub struct TransferControl<'a> {
+    tc: *mut sys::ftdi_transfer_control,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> TransferControl<'a> {
+    /// Waits for the transfer to finish, and returns the number of transferred bytes
+    pub fn wait(self) -> Result<usize, FtdiError> {
+        let tc = self.tc;
+        mem::forget(self);
+        match unsafe { sys::ftdi_transfer_data_done(tc) } {
+            code if code < 0 => Err(FtdiError::Other(code, "USB transfer failed")),
+            len => Ok(len as usize),
+        }
+    }
+    /// Cancels the transfer, and waits until the cancellation has finished.
+    ///
+    /// libftdi1 keeps handling USB events until the transfer has completed, so `timeout` only
+    /// limits each libusb event handling call, not the whole cancellation.
+    pub fn cancel(self, timeout: Duration) {
+        let tc = self.tc;
+        mem::forget(self);
+        let mut timeout = sys::timeval {
+            tv_sec: timeout.as_secs() as _,
+            tv_usec: timeout.subsec_micros() as _,
+        };
         unsafe {
-            let mut ctx: Box<sys::ftdi_context> = Box::new(mem::zeroed());
-            // Bug: libftdi1 leaks memory if libusb initialization fails
-            match sys::ftdi_init(ctx.borrow_mut()) {
-                code if code < 0 => Err(FtdiError::Other(code, error_msg(ctx.borrow_mut()))),
-                _ => Ok(Context(ctx)),
-            }
+            sys::ftdi_transfer_data_cancel(tc, &mut timeout);
         }
     }
-    /// Selects the used chip interface
-    pub fn set_interface(&mut self, interface: Interface) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_set_interface(self.0.borrow_mut(), interface as u32) } {
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+}
+
+impl<'a> Drop for TransferControl<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::ftdi_transfer_data_cancel(self.tc, ptr::null_mut());
         }
     }
-    pub fn raw_mut(&mut self) -> *mut sys::ftdi_context {
-        self.0.borrow_mut()
+}
+
+/// A libftdi1 context.
+///
+/// Whether a device is open is tracked in the type: a `Context` (short for `Context<Closed>`)
+/// is used to configure the context and to find devices, and the `usb_open*` methods consume
+/// it and return a `Device` (`Context<Open>`), which has the methods that talk to the device.
+/// `Device::usb_close` turns the device back into a `Context`, and dropping a `Device` closes
+/// it.
+///
+/// Device methods can still fail with `FtdiError::UsbDeviceUnavailable` if the device is
+/// disconnected, or its handle is closed through `raw_mut`, which `is_open` reports.
+pub struct Context<S = Closed>(*mut sys::ftdi_context, Box<State>, PhantomData<S>);
+
+/// State of a `Context` that has no device open
+#[derive(Debug)]
+pub enum Closed {}
+
+/// State of a `Context` that has a device open
+#[derive(Debug)]
+pub enum Open {}
+
+/// A context with an open device, returned by the `Context::usb_open*` methods
+pub type Device = Context<Open>;
+
+/// An error from a method that consumes a context, together with the context.
+///
+/// libftdi1 leaves no device open when these methods fail, so the context can be used to try
+/// again.
+#[derive(Debug)]
+pub struct ContextError {
+    context: Context,
+    error: FtdiError,
+}
+
+impl ContextError {
+    /// Returns the error
+    pub fn error(&self) -> FtdiError {
+        self.error
+    }
+    /// Returns the context
+    pub fn into_context(self) -> Context {
+        self.context
     }
 }
 
-impl Context {
-    /// Opens the first FTDI device that has the given vendor and product id
-    pub fn usb_open(&mut self, vendor: u16, product: u16) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_open(self.raw_mut(), vendor as c_int, product as c_int) } {
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Error for ContextError {}
+
+impl From<ContextError> for FtdiError {
+    fn from(err: ContextError) -> FtdiError {
+        err.error
+    }
+}
+
+/// Owner of a raw libftdi1 context, which frees it with `ftdi_free`.
+///
+/// A `Context` shares its raw context with the `DeviceInfo`s it has found, because their libusb
+/// devices belong to the context's libusb session, so the context is only freed once all of
+/// them have been dropped.
+pub(crate) struct RawContext(pub(crate) *mut sys::ftdi_context);
+
+/// Only the `Context` uses the raw context, and `DeviceInfo`s just keep it alive.
+unsafe impl Send for RawContext {}
+unsafe impl Sync for RawContext {}
+
+impl Drop for RawContext {
+    fn drop(&mut self) {
+        unsafe {
+            sys::ftdi_free(self.0);
         }
     }
-    /// Resets the FTDI device
-    pub fn usb_reset(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_reset(self.0.borrow_mut()) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+}
+
+/// Context state that libftdi1 doesn't keep track of.
+///
+/// It is boxed in the context, so that a `ContextError` stays small.
+struct State {
+    /// Parameters of the last successful open call, used by `reopen`
+    open_params: Option<OpenParams>,
+    /// Data registered with `eeprom_set_user_data`, which libftdi1 keeps a pointer to
+    eeprom_user_data: Vec<u8>,
+    /// Pin mask and mode of the last successful `set_bit_mode` call
+    bit_mode: Option<(u8, BitMode)>,
+    /// Owner of the raw context, shared with the `DeviceInfo`s found by `find_all`
+    raw: Arc<RawContext>,
+}
+
+impl State {
+    fn new(ctx: *mut sys::ftdi_context) -> State {
+        State {
+            open_params: None,
+            eeprom_user_data: Vec::new(),
+            bit_mode: None,
+            raw: Arc::new(RawContext(ctx)),
         }
     }
-    /// Clears the read buffer on the chip and the internal read buffer
-    pub fn usb_purge_rx_buffer(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_purge_rx_buffer(self.0.borrow_mut()) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+}
+
+/// Parameters of the last successful open call
+#[derive(Clone, Debug)]
+enum OpenParams {
+    Desc {
+        vendor: u16,
+        product: u16,
+        description: Option<String>,
+        serial: Option<String>,
+        index: u32,
+    },
+    BusAddr {
+        bus: u8,
+        addr: u8,
+    },
+    String(String),
+}
+
+/// A context can be moved to another thread, because libftdi1 and libusb don't tie contexts or
+/// device handles to the thread that created them.
+///
+/// `Context` is deliberately not `Sync`: libftdi1 contexts have no internal locking, so using
+/// the same context from several threads at the same time would be unsound.
+unsafe impl<S> Send for Context<S> {}
+
+impl<S> fmt::Debug for Context<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (baud_rate, chip_type) = unsafe { ((*self.0).baudrate, (*self.0).type_) };
+        let open = self.is_open();
+        f.debug_struct("Context")
+            .field("open", &open)
+            .field(
+                "chip_type",
+                &Some(ChipType::from_raw(chip_type)).filter(|_| open),
+            )
+            .field("interface", &self.interface())
+            .field("baud_rate", &Some(baud_rate).filter(|&baud| baud > 0))
+            .finish()
+    }
+}
+
+impl Context {
+    /// Creates and initializes a new FTDI context
+    pub fn new() -> Result<Context, FtdiError> {
+        let ctx = unsafe { sys::ftdi_new() };
+        if ctx.is_null() {
+            Err(FtdiError::Other(
+                -1,
+                "failed to allocate or initialize FTDI context",
+            ))
+        } else {
+            Ok(Context(ctx, Box::new(State::new(ctx)), PhantomData))
         }
     }
-    /// Clears the write buffer on the chip
-    pub fn usb_purge_tx_buffer(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_purge_tx_buffer(self.0.borrow_mut()) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
+    /// Allocates and initializes a new context, and selects the used chip interface
+    pub fn with_interface(interface: Interface) -> Result<Context, FtdiError> {
+        let mut ctx = Context::new()?;
+        ctx.set_interface(interface)?;
+        Ok(ctx)
+    }
+    /// Selects the used chip interface.
+    ///
+    /// Use a separate context for each interface to use several interfaces of the same chip.
+    pub fn set_interface(&mut self, interface: Interface) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_set_interface(self.0, interface as u32) } {
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
-    /// Clears the buffers on the chip and the internal read buffer
-    pub fn usb_purge_buffers(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_purge_buffers(self.0.borrow_mut()) } {
-            -3 => Err(FtdiError::UsbDeviceUnavailable),
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+    /// Selects how the kernel driver (e.g. ftdi_sio on Linux) is handled when opening a device.
+    ///
+    /// This must be set before opening a device.
+    pub fn set_module_detach_mode(&mut self, mode: ModuleDetachMode) {
+        unsafe { (*self.0).module_detach_mode = mode as u32 }
+    }
+    /// Overrides the bulk endpoint addresses, e.g. for clone devices with non-standard
+    /// endpoints.
+    ///
+    /// - `write_ep`: endpoint used for writes, 0x02 by default for interface A
+    /// - `read_ep`: endpoint used for reads, 0x81 by default for interface A
+    ///
+    /// libftdi1 names these from the chip's point of view, so `write_ep` is stored in
+    /// `ftdi_context::in_ep` and `read_ep` in `ftdi_context::out_ep`.
+    ///
+    /// This must be set before opening a device, and after `set_interface`, which resets the
+    /// endpoints to the defaults of the selected interface.
+    pub fn set_endpoints(&mut self, write_ep: u8, read_ep: u8) {
+        unsafe {
+            (*self.0).in_ep = c_int::from(write_ep);
+            (*self.0).out_ep = c_int::from(read_ep);
         }
     }
-    /// Closes the FTDI device
-    pub fn usb_close(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_usb_close(self.0.borrow_mut()) } {
-            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+    /// Returns the version of the linked libftdi1 library
+    pub fn get_library_version() -> Version {
+        let version = unsafe { sys::ftdi_get_library_version() };
+        Version {
+            major: version.major,
+            minor: version.minor,
+            micro: version.micro,
+            version_str: owned_string(version.version_str),
+            snapshot_str: owned_string(version.snapshot_str),
         }
     }
 }
 
-impl Context {
-    /// Reads the FTDIChip-ID from R-type devices
-    pub fn read_chip_id(&mut self) -> Result<u32, FtdiError> {
+impl<S> Context<S> {
+    /// Returns the selected chip interface.
+    ///
+    /// A new context uses interface A until another one is selected.
+    pub fn interface(&self) -> Interface {
+        Interface::try_from(unsafe { (*self.0).index } as u32).unwrap_or(Interface::A)
+    }
+    /// Returns true if a USB device is currently open.
+    ///
+    /// A `Device` is open when it's returned by a `usb_open*` method, but its handle can be
+    /// closed behind its back, e.g. with `ftdi_usb_close` through `raw_mut`.
+    pub fn is_open(&self) -> bool {
+        !unsafe { (*self.0).usb_dev.is_null() }
+    }
+    /// Gets the USB read timeout (in milliseconds).
+    ///
+    /// The default is 5000 milliseconds.
+    pub fn read_timeout(&self) -> i32 {
+        unsafe { (*self.0).usb_read_timeout }
+    }
+    /// Sets the USB read timeout (in milliseconds)
+    pub fn set_read_timeout(&mut self, millis: i32) {
+        unsafe { (*self.0).usb_read_timeout = millis }
+    }
+    /// Gets the USB write timeout (in milliseconds).
+    ///
+    /// The default is 5000 milliseconds.
+    pub fn write_timeout(&self) -> i32 {
+        unsafe { (*self.0).usb_write_timeout }
+    }
+    /// Sets the USB write timeout (in milliseconds)
+    pub fn set_write_timeout(&mut self, millis: i32) {
+        unsafe { (*self.0).usb_write_timeout = millis }
+    }
+    /// Gets the write buffer chunk size (in bytes).
+    ///
+    /// The default is 4096 bytes.
+    pub fn get_write_chunk_size(&mut self) -> Result<u32, FtdiError> {
         let mut result = 0;
-        match unsafe { sys::ftdi_read_chipid(self.0.borrow_mut(), &mut result) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
+        match unsafe { sys::ftdi_write_data_get_chunksize(self.0, &mut result) } {
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(result),
         }
     }
-    /// Gets the latency timer value (in milliseconds)
-    pub fn get_latency_timer(&mut self) -> Result<u8, FtdiError> {
+    /// Sets the write buffer chunk size (in bytes)
+    pub fn set_write_chunk_size(&mut self, size: u32) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_write_data_set_chunksize(self.0, size) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Gets the read buffer chunk size (in bytes).
+    ///
+    /// The default is 4096 bytes.
+    pub fn get_read_chunk_size(&mut self) -> Result<u32, FtdiError> {
         let mut result = 0;
-        match unsafe { sys::ftdi_get_latency_timer(self.0.borrow_mut(), &mut result) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
+        match unsafe { sys::ftdi_read_data_get_chunksize(self.0, &mut result) } {
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(result),
         }
     }
-    /// Sets the latency timer value (in milliseconds)
-    pub fn set_latency_timer(&mut self, millis: u8) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_set_latency_timer(self.0.borrow_mut(), millis) } {
-            -3 => Err(FtdiError::UsbDeviceUnavailable),
+    /// Sets the read buffer chunk size (in bytes)
+    pub fn set_read_chunk_size(&mut self, size: u32) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_read_data_set_chunksize(self.0, size) } {
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
-    /// Enable/disable bitbang modes
-    pub fn set_bit_mode(&mut self, mask: u8, bit_mode: BitMode) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_set_bitmode(self.0.borrow_mut(), mask, bit_mode as u8) } {
-            -2 => Err(FtdiError::UsbDeviceUnavailable),
+    /// Returns the last error message reported by libftdi1.
+    ///
+    /// This is useful after calling libftdi1 functions directly with `raw_mut`. libftdi1 error
+    /// messages are static strings.
+    pub fn error_string(&mut self) -> &'static str {
+        error_msg(self.0)
+    }
+    pub fn raw_mut(&mut self) -> *mut sys::ftdi_context {
+        self.0
+    }
+    /// Closes the device, if one is open
+    fn close_device(&mut self) -> Result<(), FtdiError> {
+        if !self.is_open() {
+            return Ok(());
+        }
+        match unsafe { sys::ftdi_usb_close(self.0) } {
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
-    /// Disable bitbang mode.
-    ///
-    /// Equivalent to `set_bit_mode(0, BitMode::Reset)`.
-    pub fn disable_bit_bang(&mut self) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_disable_bitbang(self.0.borrow_mut()) } {
+    fn into_state<T>(self) -> Context<T> {
+        let ctx = ManuallyDrop::new(self);
+        // The raw context and the state move to the returned context, which frees them
+        Context(ctx.0, unsafe { ptr::read(&ctx.1) }, PhantomData)
+    }
+}
+
+impl Context {
+    /// Finds all FTDI devices that have the given vendor and product id.
+    ///
+    /// Passing 0 as both the vendor and product id finds all devices with the default FTDI
+    /// vendor and product ids.
+    pub fn find_all(&mut self, vendor: u16, product: u16) -> Result<DeviceList<'_>, FtdiError> {
+        let mut list = ptr::null_mut();
+        match unsafe {
+            sys::ftdi_usb_find_all(self.raw_mut(), &mut list, vendor as c_int, product as c_int)
+        } {
+            code if code < 0 => {
+                // libftdi1 may leave a partial list behind on failure
+                unsafe { sys::ftdi_list_free(&mut list) };
+                Err(FtdiError::Other(code, error_msg(self.raw_mut())))
+            }
+            _ => Ok(unsafe { DeviceList::new(list, Arc::clone(&self.1.raw)) }),
+        }
+    }
+    /// Opens a device found by `find_all`.
+    ///
+    /// The `DeviceInfo` keeps the underlying libusb device alive, so the device can be opened
+    /// after the `DeviceList` it came from has been dropped. The device must have been found by
+    /// this context, because it belongs to the context's libusb session, and other devices are
+    /// rejected with `FtdiError::InvalidArgument`. `Device::reopen` opens the device again by
+    /// bus number and device address.
+    pub fn usb_open_dev(self, dev: &DeviceInfo) -> Result<Device, ContextError> {
+        if !dev.belongs_to(self.0) {
+            return Err(ContextError {
+                context: self,
+                error: FtdiError::InvalidArgument("device was found by another context"),
+            });
+        }
+        match unsafe { sys::ftdi_usb_open_dev(self.0, dev.raw()) } {
+            code if code < 0 => {
+                let error = open_error(self.0, code);
+                Err(ContextError {
+                    context: self,
+                    error,
+                })
+            }
+            _ => Ok(self.into_device(Some(OpenParams::BusAddr {
+                bus: dev.bus_number(),
+                addr: dev.device_address(),
+            }))),
+        }
+    }
+    /// Opens the first FTDI device that has the given vendor and product id
+    pub fn usb_open(self, vendor: u16, product: u16) -> Result<Device, ContextError> {
+        self.open(OpenParams::Desc {
+            vendor,
+            product,
+            description: None,
+            serial: None,
+            index: 0,
+        })
+    }
+    /// Opens the first FTDI device that has the given vendor and product id, and optionally
+    /// matches the given description and serial number strings
+    pub fn usb_open_desc(
+        self,
+        vendor: u16,
+        product: u16,
+        description: Option<&str>,
+        serial: Option<&str>,
+    ) -> Result<Device, ContextError> {
+        self.open(OpenParams::Desc {
+            vendor,
+            product,
+            description: description.map(String::from),
+            serial: serial.map(String::from),
+            index: 0,
+        })
+    }
+    /// Opens the `index`th FTDI device that has the given vendor and product id, and optionally
+    /// matches the given description and serial number strings.
+    ///
+    /// The index is zero-based and counts only the matching devices.
+    pub fn usb_open_desc_index(
+        self,
+        vendor: u16,
+        product: u16,
+        description: Option<&str>,
+        serial: Option<&str>,
+        index: u32,
+    ) -> Result<Device, ContextError> {
+        self.open(OpenParams::Desc {
+            vendor,
+            product,
+            description: description.map(String::from),
+            serial: serial.map(String::from),
+            index,
+        })
+    }
+    /// Opens the FTDI device at the given USB bus number and device address
+    pub fn usb_open_bus_addr(self, bus: u8, addr: u8) -> Result<Device, ContextError> {
+        self.open(OpenParams::BusAddr { bus, addr })
+    }
+    /// Opens the FTDI device described by a libftdi1 device string.
+    ///
+    /// Supported formats:
+    ///
+    /// * `d:<bus>/<device>`: device node path in the USB device tree, e.g. `d:003/001`
+    /// * `i:<vendor>:<product>`: first device with the given vendor and product id
+    /// * `i:<vendor>:<product>:<index>`: `index`th (zero-based) device with the given vendor and
+    ///   product id
+    /// * `s:<vendor>:<product>:<serial>`: first device with the given vendor id, product id and
+    ///   serial number
+    ///
+    /// Ids can be decimal, octal (prefixed with `0`) or hexadecimal (prefixed with `0x`).
+    /// A malformed string is reported as libftdi1 error code -11.
+    pub fn usb_open_string(self, desc: &str) -> Result<Device, ContextError> {
+        self.open(OpenParams::String(desc.to_owned()))
+    }
+    /// Opens the first FTDI device that has the given vendor and product id, calls `f`, and
+    /// closes the device.
+    ///
+    /// Returns the context together with the result of `f`, or with the error. The device is
+    /// closed even if `f` fails or panics, since dropping it during unwinding closes it too. An
+    /// error from `f` takes precedence over an error from closing.
+    pub fn with_usb<F, R>(
+        self,
+        vendor: u16,
+        product: u16,
+        f: F,
+    ) -> Result<(Context, R), ContextError>
+    where
+        F: FnOnce(&mut Device) -> Result<R, FtdiError>,
+    {
+        let mut device = self.usb_open(vendor, product)?;
+        let result = f(&mut device);
+        match (result, device.usb_close()) {
+            (Ok(value), Ok(context)) => Ok((context, value)),
+            (Ok(_), Err(err)) => Err(err),
+            (Err(error), Ok(context)) | (Err(error), Err(ContextError { context, .. })) => {
+                Err(ContextError { context, error })
+            }
+        }
+    }
+    /// Opens the device with the given vendor id, product id and serial number, retrying until
+    /// it appears or `timeout` passes.
+    ///
+    /// `FtdiError::DeviceNotFound` and `FtdiError::AccessDenied` are retried every 100
+    /// milliseconds, because a replugged device may briefly be visible before its permissions
+    /// have been set up. Other errors are returned immediately, and `FtdiError::Timeout` is
+    /// returned if the device doesn't appear in time. To reconnect a `Device` whose handle is
+    /// stale after an unplug, close it first, ignoring errors.
+    pub fn reconnect_by_serial(
+        mut self,
+        vendor: u16,
+        product: u16,
+        serial: &str,
+        timeout: Duration,
+    ) -> Result<Device, ContextError> {
+        let deadline = Instant::now() + timeout;
+        let params = OpenParams::Desc {
+            vendor,
+            product,
+            description: None,
+            serial: Some(serial.to_owned()),
+            index: 0,
+        };
+        loop {
+            match self.open_raw(&params) {
+                Ok(()) => return Ok(self.into_device(Some(params))),
+                Err(FtdiError::DeviceNotFound) | Err(FtdiError::AccessDenied) => {
+                    if Instant::now() >= deadline {
+                        return Err(ContextError {
+                            context: self,
+                            error: FtdiError::Timeout,
+                        });
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(error) => {
+                    return Err(ContextError {
+                        context: self,
+                        error,
+                    })
+                }
+            }
+        }
+    }
+    /// Uses an already opened libusb device handle.
+    ///
+    /// # Safety
+    ///
+    /// `dev` must be a valid, open libusb device handle for an FTDI device. libftdi1 takes over
+    /// ownership of the handle: it is closed when the device is closed or dropped, so the caller
+    /// must not close or use it afterwards. The handle's libusb context must outlive the
+    /// returned device.
+    pub unsafe fn set_usb_dev(self, dev: *mut sys::libusb_device_handle) -> Device {
+        sys::ftdi_set_usbdev(self.0, dev);
+        self.into_device(None)
+    }
+    fn open(mut self, params: OpenParams) -> Result<Device, ContextError> {
+        match self.open_raw(&params) {
+            Ok(()) => Ok(self.into_device(Some(params))),
+            Err(error) => Err(ContextError {
+                context: self,
+                error,
+            }),
+        }
+    }
+    fn open_raw(&mut self, params: &OpenParams) -> Result<(), FtdiError> {
+        let matches_description = matches!(
+            params,
+            OpenParams::Desc {
+                description: Some(_),
+                ..
+            }
+        );
+        let code = match params {
+            OpenParams::Desc {
+                vendor,
+                product,
+                description,
+                serial,
+                index,
+            } => {
+                let description = to_c_string(description.as_deref())?;
+                let serial = to_c_string(serial.as_deref())?;
+                unsafe {
+                    sys::ftdi_usb_open_desc_index(
+                        self.0,
+                        c_int::from(*vendor),
+                        c_int::from(*product),
+                        c_string_ptr(&description),
+                        c_string_ptr(&serial),
+                        *index,
+                    )
+                }
+            }
+            OpenParams::BusAddr { bus, addr } => unsafe {
+                sys::ftdi_usb_open_bus_addr(self.0, *bus, *addr)
+            },
+            OpenParams::String(desc) => {
+                let desc = to_c_string(Some(desc))?;
+                unsafe { sys::ftdi_usb_open_string(self.0, c_string_ptr(&desc)) }
+            }
+        };
+        match code {
+            // The search functions use -3 if no device matches. ftdi_usb_open_dev also uses it
+            // if setting the USB configuration fails, which can't be told apart here.
+            -3 => Err(FtdiError::DeviceNotFound),
+            // ftdi_usb_open_desc_index uses -8 if it can't read a product description to match
+            -8 if matches_description => Err(FtdiError::Other(code, error_msg(self.0))),
+            code if code < 0 => Err(open_error(self.0, code)),
+            _ => Ok(()),
+        }
+    }
+    fn into_device(mut self, params: Option<OpenParams>) -> Device {
+        self.1.open_params = params;
+        self.into_state()
+    }
+}
+
+impl Device {
+    /// Returns the chip type detected when the device was opened
+    pub fn chip_type(&self) -> ChipType {
+        ChipType::from_raw(unsafe { (*self.0).type_ })
+    }
+    /// Returns the maximum packet size of the device's bulk endpoints.
+    ///
+    /// This is 64 bytes for full-speed chips and 512 bytes for high-speed chips. Every packet
+    /// read from the chip starts with two status bytes.
+    pub fn max_packet_size(&self) -> u32 {
+        unsafe { (*self.0).max_packet_size }
+    }
+    /// Closes the device and opens it again with the parameters of the `usb_open*` call that
+    /// opened it.
+    ///
+    /// Useful after `usb_reset`, if the device re-enumerates and the old handle becomes stale.
+    /// Errors from closing the stale handle are ignored. Opening is retried for up to a second
+    /// while the device is not found. Devices opened with `usb_open_bus_addr` are reopened by
+    /// bus number and device address, which may change on re-enumeration.
+    ///
+    /// Fails with `FtdiError::UsbDeviceUnavailable` if the device wasn't opened with a
+    /// `usb_open*` method. The device is closed in that case too.
+    pub fn reopen(self) -> Result<Device, ContextError> {
+        let params = self.1.open_params.clone();
+        let mut ctx = self.usb_close().unwrap_or_else(ContextError::into_context);
+        let params = match params {
+            Some(params) => params,
+            None => {
+                return Err(ContextError {
+                    context: ctx,
+                    error: FtdiError::UsbDeviceUnavailable,
+                })
+            }
+        };
+        let mut attempts = 10;
+        loop {
+            thread::sleep(Duration::from_millis(100));
+            attempts -= 1;
+            match ctx.open_raw(&params) {
+                Ok(()) => return Ok(ctx.into_device(Some(params))),
+                Err(FtdiError::DeviceNotFound) if attempts > 0 => continue,
+                Err(error) => {
+                    return Err(ContextError {
+                        context: ctx,
+                        error,
+                    })
+                }
+            }
+        }
+    }
+    /// Resets the FTDI device.
+    ///
+    /// Fails with `FtdiError::ControlTransferFailed` if the device didn't accept the reset
+    /// request.
+    pub fn usb_reset(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_usb_reset(self.0) } {
+            -1 => Err(FtdiError::ControlTransferFailed(error_msg(self.raw_mut()))),
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Resets the FTDI device, and restores the pin mask and mode of the last successful
+    /// `set_bit_mode` call.
+    ///
+    /// Nothing is restored if bit-bang mode was never enabled or was disabled with
+    /// `disable_bit_bang`.
+    pub fn usb_reset_preserving_mode(&mut self) -> Result<(), FtdiError> {
+        let bit_mode = self.1.bit_mode;
+        self.usb_reset()?;
+        if let Some((mask, bit_mode)) = bit_mode {
+            self.set_bit_mode(mask, bit_mode)?;
+        }
+        Ok(())
+    }
+    /// Resets the FTDI device, and waits until it responds again or `timeout` passes.
+    ///
+    /// Reachability is checked by polling the modem status every 10 milliseconds. Fails with
+    /// `FtdiError::Timeout` if the device doesn't respond in time. If the device re-enumerates
+    /// and the handle becomes stale, it never responds again; use `reopen` in that case.
+    pub fn usb_reset_wait(&mut self, timeout: Duration) -> Result<(), FtdiError> {
+        let deadline = Instant::now() + timeout;
+        self.usb_reset()?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if self.poll_modem_status_timeout(remaining).is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(FtdiError::Timeout);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    /// Returns the device to a known default state.
+    ///
+    /// The steps are performed in this order, stopping at the first error:
+    ///
+    /// 1. `usb_reset`
+    /// 2. `disable_bit_bang`
+    /// 3. `set_latency_timer(16)`
+    /// 4. `set_flow_control(FlowControl::None)`
+    /// 5. `tcio_flush`
+    ///
+    /// The buffers are flushed last so that no data produced by the earlier steps is left
+    /// behind.
+    pub fn reset_to_defaults(&mut self) -> Result<(), FtdiError> {
+        self.usb_reset()?;
+        self.disable_bit_bang()?;
+        self.set_latency_timer(16)?;
+        self.set_flow_control(FlowControl::None)?;
+        self.tcio_flush()
+    }
+    /// Clears the read buffer on the chip and the internal read buffer
+    #[deprecated(note = "use `tci_flush` instead")]
+    pub fn usb_purge_rx_buffer(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_usb_purge_rx_buffer(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Clears the write buffer on the chip
+    #[deprecated(note = "use `tco_flush` instead")]
+    pub fn usb_purge_tx_buffer(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_usb_purge_tx_buffer(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Clears the buffers on the chip and the internal read buffer
+    #[deprecated(note = "use `tcio_flush` instead")]
+    pub fn usb_purge_buffers(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_usb_purge_buffers(self.0) } {
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Clears the read buffer on the chip and the internal read buffer
+    pub fn tci_flush(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_tciflush(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Clears the read buffers, and discards data until the device has no more pending data.
+    ///
+    /// Data that arrives right after the flush is read and discarded until a read returns no
+    /// data, which takes roughly one latency timer period. Returns the number of discarded
+    /// bytes.
+    pub fn purge_and_reset_read_buffer(&mut self) -> Result<usize, FtdiError> {
+        self.tci_flush()?;
+        let mut buf = [0; 512];
+        let mut discarded = 0;
+        loop {
+            match self.read_available(&mut buf)? {
+                0 => return Ok(discarded),
+                len => discarded += len,
+            }
+        }
+    }
+    /// Reads and discards all currently available input without blocking, and returns the
+    /// number of discarded bytes.
+    ///
+    /// Unlike `tci_flush`, this drains data already held in the internal read buffer by
+    /// reading it. The USB read timeout is lowered to 1 ms while draining, and restored
+    /// afterwards. Draining stops at the first read that returns no data, which is usually a
+    /// read that times out, since the chip holds back small amounts of data until the latency
+    /// timer expires. Such data is not drained.
+    pub fn drain_input(&mut self) -> Result<usize, FtdiError> {
+        let timeout = self.read_timeout();
+        self.set_read_timeout(1);
+        let mut buf = [0; 512];
+        let mut discarded = 0;
+        let result = loop {
+            match self.read_available(&mut buf) {
+                // Also returned if the read timed out
+                Ok(0) => break Ok(discarded),
+                Ok(len) => discarded += len,
+                Err(err) => break Err(err),
+            }
+        };
+        self.set_read_timeout(timeout);
+        result
+    }
+    /// Clears the write buffer on the chip
+    pub fn tco_flush(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_tcoflush(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Clears the buffers on the chip and the internal read buffer
+    pub fn tcio_flush(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_tcioflush(self.0) } {
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Closes the FTDI device, and returns the context for opening a device again.
+    ///
+    /// libftdi1 closes the device handle even if releasing the USB interface fails, so the
+    /// context is also returned with the error.
+    pub fn usb_close(mut self) -> Result<Context, ContextError> {
+        let result = self.close_device();
+        let context = self.into_state();
+        match result {
+            Ok(()) => Ok(context),
+            Err(error) => Err(ContextError { context, error }),
+        }
+    }
+    /// Returns the vendor id of the open device
+    pub fn vendor_id(&self) -> Result<u16, FtdiError> {
+        Ok(self.device_descriptor()?.idVendor)
+    }
+    /// Returns the product id of the open device
+    pub fn product_id(&self) -> Result<u16, FtdiError> {
+        Ok(self.device_descriptor()?.idProduct)
+    }
+    /// Returns the libusb device handle.
+    ///
+    /// Fails with `FtdiError::UsbDeviceUnavailable` if the handle has been closed behind the
+    /// device's back, e.g. by libftdi1 after an error or through `raw_mut`.
+    fn usb_dev(&self) -> Result<*mut sys::libusb_device_handle, FtdiError> {
+        let usb_dev = unsafe { (*self.0).usb_dev };
+        if usb_dev.is_null() {
+            Err(FtdiError::UsbDeviceUnavailable)
+        } else {
+            Ok(usb_dev)
+        }
+    }
+    fn device_descriptor(&self) -> Result<sys::libusb_device_descriptor, FtdiError> {
+        let usb_dev = self.usb_dev()?;
+        let mut desc = MaybeUninit::uninit();
+        match unsafe {
+            sys::libusb_get_device_descriptor(sys::libusb_get_device(usb_dev), desc.as_mut_ptr())
+        } {
+            code if code < 0 => Err(FtdiError::Other(
+                code,
+                "failed to get USB device descriptor",
+            )),
+            _ => Ok(unsafe { desc.assume_init() }),
+        }
+    }
+    /// Reads the manufacturer, product and serial number strings from the USB descriptors of
+    /// the open device.
+    ///
+    /// Unlike `eeprom_get_strings`, this queries the live device. The device stays open if a
+    /// string can't be read, e.g. because the device has no serial number string.
+    pub fn usb_get_strings(&mut self) -> Result<DeviceStrings, FtdiError> {
+        let usb_dev = self.usb_dev()?;
+        let desc = self.device_descriptor()?;
+        Ok(DeviceStrings {
+            manufacturer: string_descriptor(
+                usb_dev,
+                desc.iManufacturer,
+                "device has no manufacturer string",
+            )?,
+            product: string_descriptor(usb_dev, desc.iProduct, "device has no product string")?,
+            serial: string_descriptor(
+                usb_dev,
+                desc.iSerialNumber,
+                "device has no serial number string",
+            )?,
+        })
+    }
+}
+
+impl Device {
+    /// Reads the FTDIChip-ID from R-type devices
+    pub fn read_chip_id(&mut self) -> Result<u32, FtdiError> {
+        let mut result = 0;
+        match unsafe { sys::ftdi_read_chipid(self.0, &mut result) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(result),
+        }
+    }
+    /// Sets the chip baud rate, and returns the actual baud rate.
+    ///
+    /// The chip can only generate rates of the form base clock / divisor, so the actual rate
+    /// may differ from the requested one. libftdi1 rejects rates that would be off by more than
+    /// about 5% with `FtdiError::InvalidBaudRate`.
+    ///
+    /// In bit-bang mode, libftdi1 multiplies the rate by 4 before programming the chip, and
+    /// the returned rate is divided by 4 again.
+    pub fn set_baud_rate(&mut self, baud: u32) -> Result<u32, FtdiError> {
+        match unsafe { sys::ftdi_set_baudrate(self.0, baud as c_int) } {
+            -1 => Err(FtdiError::InvalidBaudRate),
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(self.actual_baud_rate(baud)),
+        }
+    }
+    /// Returns the baud rate the chip generates for `baud`, in the same units as
+    /// `set_baud_rate`
+    fn actual_baud_rate(&self, baud: u32) -> u32 {
+        let bit_bang = unsafe { (*self.0).bitbang_enabled } != 0;
+        let multiplier = if bit_bang { 4 } else { 1 };
+        baud::actual_baud_rate(self.chip_type(), baud.saturating_mul(multiplier))
+            .map_or(baud, |actual| actual / multiplier)
+    }
+    /// Sets the line characteristics (data bits, stop bits, parity)
+    pub fn set_line_property(
+        &mut self,
+        bits: Bits,
+        stop: StopBits,
+        parity: Parity,
+    ) -> Result<(), FtdiError> {
+        match unsafe {
+            sys::ftdi_set_line_property(self.0, bits as u32, stop as u32, parity as u32)
+        } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Sets the line characteristics and the break condition.
+    ///
+    /// Enabling break holds the TX line in the break condition until this method is called again
+    /// with `break_on` set to `false`.
+    pub fn set_line_property_break(
+        &mut self,
+        bits: Bits,
+        stop: StopBits,
+        parity: Parity,
+        break_on: bool,
+    ) -> Result<(), FtdiError> {
+        let break_type = if break_on {
+            sys::ftdi_break_type_BREAK_ON
+        } else {
+            sys::ftdi_break_type_BREAK_OFF
+        };
+        match unsafe {
+            sys::ftdi_set_line_property2(
+                self.0,
+                bits as u32,
+                stop as u32,
+                parity as u32,
+                break_type,
+            )
+        } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
+    /// Gets the latency timer value (in milliseconds)
+    pub fn get_latency_timer(&mut self) -> Result<u8, FtdiError> {
+        let mut result = 0;
+        match unsafe { sys::ftdi_get_latency_timer(self.0, &mut result) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(result),
+        }
+    }
+    /// Sets the latency timer value (in milliseconds).
+    ///
+    /// The valid range is 1-255 milliseconds, and 0 is rejected with
+    /// `FtdiError::InvalidArgument`.
+    pub fn set_latency_timer(&mut self, millis: u8) -> Result<(), FtdiError> {
+        check_latency_timer(millis)?;
+        match unsafe { sys::ftdi_set_latency_timer(self.0, millis) } {
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Enable/disable bitbang modes
+    pub fn set_bit_mode(&mut self, mask: u8, bit_mode: BitMode) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_set_bitmode(self.0, mask, bit_mode as u8) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => {
+                self.1.bit_mode = Some((mask, bit_mode));
+                Ok(())
+            }
+        }
+    }
+    /// Returns the bit mode last set with `set_bit_mode`.
+    ///
+    /// Returns `BitMode::Reset` if bit-bang mode has been disabled with `disable_bit_bang`, or
+    /// if libftdi1 reports a mode this crate doesn't know.
+    pub fn bit_mode(&self) -> BitMode {
+        let (enabled, mode) = unsafe { ((*self.0).bitbang_enabled, (*self.0).bitbang_mode) };
+        if enabled == 0 {
+            return BitMode::Reset;
+        }
+        BitMode::from_raw(u32::from(mode)).unwrap_or(BitMode::Reset)
+    }
+    /// Disable bitbang mode.
+    ///
+    /// Equivalent to `set_bit_mode(0, BitMode::Reset)`.
+    pub fn disable_bit_bang(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_disable_bitbang(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => {
+                self.1.bit_mode = None;
+                Ok(())
+            }
+        }
+    }
     /// Directly read pin state, circumventing the read buffer
     pub fn read_pins(&mut self) -> Result<u8, FtdiError> {
         let mut result = 0;
-        match unsafe { sys::ftdi_read_pins(self.0.borrow_mut(), &mut result) } {
+        match unsafe { sys::ftdi_read_pins(self.0, &mut result) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(result),
         }
     }
+    /// Directly read pin state as `Pins`, circumventing the read buffer
+    pub fn read_pin_state(&mut self) -> Result<Pins, FtdiError> {
+        self.read_pins().map(Pins)
+    }
+    /// Enables CBUS bit-bang mode and sets the directions and values of the 4 CBUS pins.
+    ///
+    /// Only the low nibbles of `directions` and `values` are used, and a direction bit set to 1
+    /// makes the pin an output. Bit 0 corresponds to the first configurable pin:
+    ///
+    /// * FT232R: CBUS0-CBUS3 (CBUS4 can't be used for bit-bang)
+    /// * FT230X/FT231X/FT234X: CBUS0-CBUS3
+    /// * FT232H: ACBUS5, ACBUS6, ACBUS8 and ACBUS9
+    ///
+    /// The pins must also be configured as GPIO ("I/O mode") in the EEPROM.
+    pub fn set_cbus(&mut self, directions: u8, values: u8) -> Result<(), FtdiError> {
+        let mask = (directions & 0x0f) << 4 | (values & 0x0f);
+        self.set_bit_mode(mask, BitMode::Cbus)
+    }
+    /// Reads the state of the 4 CBUS pins in CBUS bit-bang mode
+    pub fn read_cbus(&mut self) -> Result<u8, FtdiError> {
+        Ok(self.read_pins()? & 0x0f)
+    }
     /// Poll modem status information
     pub fn poll_modem_status(&mut self) -> Result<ModemStatus, FtdiError> {
         let mut result = 0;
-        match unsafe { sys::ftdi_poll_modem_status(self.0.borrow_mut(), &mut result) } {
+        match unsafe { sys::ftdi_poll_modem_status(self.0, &mut result) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(ModemStatus::from_bits_truncate(result)),
         }
     }
+    /// Poll modem status information, blocking for at most `timeout`.
+    ///
+    /// The USB read timeout is temporarily replaced with `timeout` (rounded up to whole
+    /// milliseconds) and restored afterwards, so a disconnected device is detected quickly.
+    pub fn poll_modem_status_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<ModemStatus, FtdiError> {
+        // libusb treats a zero timeout as unlimited
+        let millis = timeout
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .clamp(1, i32::MAX as u128) as i32;
+        let previous = self.read_timeout();
+        self.set_read_timeout(millis);
+        let result = self.poll_modem_status();
+        self.set_read_timeout(previous);
+        result
+    }
     /// Sets both the Data Terminal Ready (DTR) and Request To Send (RTS) signals
     pub fn set_dtr_rts(&mut self, dtr: bool, rts: bool) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_setdtr_rts(self.0.borrow_mut(), dtr as _, rts as _) } {
+        match unsafe { sys::ftdi_setdtr_rts(self.0, dtr as _, rts as _) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
+    /// Sets both the DTR and RTS signals, and then polls the modem status.
+    ///
+    /// This is a shorthand for `set_dtr_rts` followed by `poll_modem_status`, and still uses
+    /// two USB control transfers. The returned status shows the input signals (CTS, DSR, RI,
+    /// DCD) as they were right after the change, so a slow external circuit may not have
+    /// reacted yet.
+    pub fn set_dtr_rts_and_poll(&mut self, dtr: bool, rts: bool) -> Result<ModemStatus, FtdiError> {
+        self.set_dtr_rts(dtr, rts)?;
+        self.poll_modem_status()
+    }
     /// Sets the Data Terminal Ready (DTR) signal
     pub fn set_dtr(&mut self, dtr: bool) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_setdtr(self.0.borrow_mut(), dtr as _) } {
+        match unsafe { sys::ftdi_setdtr(self.0, dtr as _) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
@@ -254,7 +1722,7 @@ impl Context {
     }
     /// Sets the Request To Send (RTS) signal
     pub fn set_rts(&mut self, rts: bool) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_setrts(self.0.borrow_mut(), rts as _) } {
+        match unsafe { sys::ftdi_setrts(self.0, rts as _) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
@@ -262,7 +1730,18 @@ impl Context {
     }
     /// Sets the flow control setting
     pub fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_setflowctrl(self.0.borrow_mut(), flow_control as i32) } {
+        match unsafe { sys::ftdi_setflowctrl(self.0, flow_control as i32) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Enables XON/XOFF flow control with the given XON and XOFF characters.
+    ///
+    /// Prefer this over `set_flow_control(FlowControl::XonXoff)`, which doesn't configure the
+    /// characters. The usual characters are 0x11 (XON) and 0x13 (XOFF).
+    pub fn set_flow_control_xon_xoff(&mut self, xon: u8, xoff: u8) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_setflowctrl_xonxoff(self.0, xon, xoff) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
@@ -270,7 +1749,7 @@ impl Context {
     }
     /// Sets and enables/disables the special event character
     pub fn set_event_char(&mut self, ch: u8, enable: bool) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_set_event_char(self.0.borrow_mut(), ch, enable as _) } {
+        match unsafe { sys::ftdi_set_event_char(self.0, ch, enable as _) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
@@ -278,23 +1757,169 @@ impl Context {
     }
     /// Sets and enables/disables the error character
     pub fn set_error_char(&mut self, ch: u8, enable: bool) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_set_error_char(self.0.borrow_mut(), ch, enable as _) } {
+        match unsafe { sys::ftdi_set_error_char(self.0, ch, enable as _) } {
             -2 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             _ => Ok(()),
         }
     }
-    pub fn write_data(&mut self, data: &[u8]) -> Result<(), FtdiError> {
-        match unsafe { sys::ftdi_write_data(self.0.borrow_mut(), data.as_ptr(), data.len() as _) } {
+    /// Disables the special event character
+    pub fn disable_event_char(&mut self) -> Result<(), FtdiError> {
+        self.set_event_char(0, false)
+    }
+    /// Disables the error character
+    pub fn disable_error_char(&mut self) -> Result<(), FtdiError> {
+        self.set_error_char(0, false)
+    }
+    pub fn write_data(&mut self, data: &[u8]) -> Result<usize, FtdiError> {
+        match unsafe { sys::ftdi_write_data(self.0, data.as_ptr(), data.len() as _) } {
             -666 => Err(FtdiError::UsbDeviceUnavailable),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
-            _ => Ok(()),
+            len => Ok(len as usize),
+        }
+    }
+    /// Writes data, looping until all bytes have been written
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), FtdiError> {
+        let mut pos = 0;
+        while pos < data.len() {
+            let len = self.write_data(&data[pos..])?;
+            pos += len;
+        }
+        Ok(())
+    }
+    /// Writes all bytes produced by `iter`, and returns the number of bytes written.
+    ///
+    /// Bytes are collected into a 4 KiB chunk, which is written with `write_all` whenever it
+    /// fills up, and once more at the end for the remainder. If a write fails, bytes of the
+    /// failed chunk may have been partially written.
+    pub fn write_iter<I: IntoIterator<Item = u8>>(&mut self, iter: I) -> Result<usize, FtdiError> {
+        let mut chunk = [0; 4096];
+        let mut len = 0;
+        let mut written = 0;
+        for byte in iter {
+            chunk[len] = byte;
+            len += 1;
+            if len == chunk.len() {
+                self.write_all(&chunk)?;
+                written += len;
+                len = 0;
+            }
         }
+        self.write_all(&chunk[..len])?;
+        Ok(written + len)
     }
+    /// Reads data, and returns the number of bytes read.
+    ///
+    /// Returns `Ok(0)` if the chip has no data to send. The chip answers reads with status-only
+    /// packets every latency timer period (see `set_latency_timer`), so an empty read usually
+    /// returns after that period, long before the USB read timeout. Fails with
+    /// `FtdiError::Timeout` if the chip doesn't answer at all within the USB read timeout.
     pub fn read_data(&mut self, buf: &mut [u8]) -> Result<usize, FtdiError> {
-        match unsafe { sys::ftdi_read_data(self.0.borrow_mut(), buf.as_mut_ptr(), buf.len() as _) }
-        {
+        match unsafe { sys::ftdi_read_data(self.0, buf.as_mut_ptr(), buf.len() as _) } {
             -666 => Err(FtdiError::UsbDeviceUnavailable),
+            // libftdi1 passes LIBUSB_ERROR_TIMEOUT through
+            -7 => Err(FtdiError::Timeout),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            len => Ok(len as usize),
+        }
+    }
+    /// Reads the data that is available without waiting for more, and returns the number of
+    /// bytes read.
+    ///
+    /// Like `read_data`, but a USB read timeout is reported as `Ok(0)` instead of
+    /// `FtdiError::Timeout`, for polling loops that treat both as no data.
+    pub fn read_available(&mut self, buf: &mut [u8]) -> Result<usize, FtdiError> {
+        match self.read_data(buf) {
+            Err(FtdiError::Timeout) => Ok(0),
+            result => result,
+        }
+    }
+    /// Reads data like `read_data`, and also returns the line and modem status sent along with
+    /// it.
+    ///
+    /// The chip prefixes every USB packet with two status bytes, which `read_data` strips and
+    /// discards. This method performs the USB read itself to keep them. The returned status is
+    /// the one of the last packet, with the error flags (`OE`, `PE`, `FE`, `BI`, `RCVR_ERR`)
+    /// of all packets combined, so an error isn't missed if more packets follow it.
+    ///
+    /// Data already buffered by libftdi1 is returned first, without a status. `buf` must have
+    /// room for the payload of at least one USB packet (62 or 510 bytes, depending on the chip).
+    pub fn read_data_with_status(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, Option<ModemStatus>), FtdiError> {
+        let usb_dev = self.usb_dev()?;
+        let (buffered, chunk_size, packet_size, endpoint, timeout) = unsafe {
+            let ctx = &*self.0;
+            (
+                ctx.readbuffer_remaining as usize,
+                ctx.readbuffer_chunksize as usize,
+                ctx.max_packet_size as usize,
+                // libftdi1 names the endpoints from the chip's point of view: `out_ep` is the
+                // bulk IN endpoint the host reads from, the same one `ftdi_read_data` uses
+                ctx.out_ep,
+                ctx.usb_read_timeout,
+            )
+        };
+        if buffered > 0 {
+            let len = buffered.min(buf.len());
+            return Ok((self.read_data(&mut buf[..len])?, None));
+        }
+        let payload_size = packet_size.saturating_sub(2);
+        if payload_size == 0 || buf.len() < payload_size {
+            return Err(FtdiError::InvalidArgument(
+                "buffer is smaller than the payload of a USB packet",
+            ));
+        }
+        // Only request as many packets as fit in `buf`, so no data has to be dropped
+        let packets = (buf.len() / payload_size).min((chunk_size / packet_size).max(1));
+        let mut raw = vec![0; packets * packet_size];
+        let mut transferred = 0;
+        match unsafe {
+            sys::libusb_bulk_transfer(
+                usb_dev,
+                endpoint as u8,
+                raw.as_mut_ptr(),
+                raw.len() as c_int,
+                &mut transferred,
+                timeout.max(0) as u32,
+            )
+        } {
+            // LIBUSB_ERROR_TIMEOUT may still have transferred some data
+            0 | -7 => (),
+            -4 => return Err(FtdiError::UsbDeviceUnavailable),
+            code => return Err(FtdiError::Other(code, "USB bulk read failed")),
+        }
+        let errors = ModemStatus::RCVR_ERR
+            | ModemStatus::BI
+            | ModemStatus::FE
+            | ModemStatus::PE
+            | ModemStatus::OE;
+        let mut status: Option<ModemStatus> = None;
+        let mut len = 0;
+        for packet in raw[..transferred as usize].chunks(packet_size) {
+            if packet.len() < 2 {
+                break;
+            }
+            let packet_status =
+                ModemStatus::from_bits_truncate(u16::from_le_bytes([packet[0], packet[1]]));
+            let previous_errors = status.map_or(ModemStatus::empty(), |status| status & errors);
+            status = Some(packet_status | previous_errors);
+            let payload = &packet[2..];
+            buf[len..len + payload.len()].copy_from_slice(payload);
+            len += payload.len();
+        }
+        Ok((len, status))
+    }
+    /// Reads data into a possibly uninitialized buffer, and returns the number of bytes read.
+    ///
+    /// On success, the first `len` elements of `buf` are initialized, where `len` is the
+    /// returned value. The rest of the buffer is left untouched, so it may still be
+    /// uninitialized.
+    pub fn read_data_uninit(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize, FtdiError> {
+        match unsafe { sys::ftdi_read_data(self.0, buf.as_mut_ptr() as *mut u8, buf.len() as _) } {
+            -666 => Err(FtdiError::UsbDeviceUnavailable),
+            -7 => Err(FtdiError::Timeout),
             code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
             len => Ok(len as usize),
         }
@@ -307,12 +1932,691 @@ impl Context {
         }
         Ok(())
     }
+    /// Reads data and appends it to `buf` until no data arrives for `idle`, and returns the
+    /// number of bytes read.
+    ///
+    /// Note that this shadows `io::Read::read_to_end`, which can still be called as
+    /// `io::Read::read_to_end(&mut ctx, buf)`.
+    pub fn read_to_end(&mut self, buf: &mut Vec<u8>, idle: Duration) -> Result<usize, FtdiError> {
+        let start_len = buf.len();
+        let mut last_data = Instant::now();
+        loop {
+            buf.reserve(4096);
+            let len = match self.read_data_uninit(buf.spare_capacity_mut()) {
+                // A USB read timeout is idle time too
+                Err(FtdiError::Timeout) => 0,
+                result => result?,
+            };
+            if len > 0 {
+                // read_data_uninit initialized the first `len` bytes of the spare capacity
+                unsafe { buf.set_len(buf.len() + len) };
+                last_data = Instant::now();
+            } else if last_data.elapsed() >= idle {
+                return Ok(buf.len() - start_len);
+            }
+        }
+    }
+    /// Like `read_exact`, but temporarily grows the read chunk size to fit `buf`, so large reads
+    /// need fewer USB transfers.
+    ///
+    /// The chunk size is rounded up to whole USB packets and grown to at most 64 KiB (libftdi1
+    /// further limits it to 16 KiB on Linux), and only if no data is buffered, since changing it
+    /// discards the internal read buffer. The previous chunk size is restored afterwards, also
+    /// on errors, keeping any data read past the end of `buf` buffered. For buffers that fit
+    /// the current chunk size this behaves exactly like `read_exact`.
+    pub fn read_exact_buffered(&mut self, buf: &mut [u8]) -> Result<(), FtdiError> {
+        const MAX_CHUNK_SIZE: usize = 65536;
+        let previous = self.get_read_chunk_size()?;
+        let packet_size = self.packet_size();
+        // Every packet starts with two status bytes
+        let packets = buf.len().div_ceil(packet_size - 2);
+        let wanted = (packets * packet_size).min(MAX_CHUNK_SIZE) as u32;
+        let buffered = unsafe { (*self.0).readbuffer_remaining };
+        if buffered != 0 || wanted <= previous {
+            return self.read_exact(buf);
+        }
+        self.set_read_chunk_size(wanted)?;
+        let result = self.read_exact(buf);
+        let restored = self.restore_read_chunk_size(previous);
+        result.and(restored)
+    }
+    /// Returns the USB packet size, assuming full-speed packets if libftdi1 doesn't know it,
+    /// e.g. for a handle passed to `set_usb_dev`
+    fn packet_size(&self) -> usize {
+        match self.max_packet_size() {
+            0 => 64,
+            size => size as usize,
+        }
+    }
+    /// Sets the read chunk size, keeping the data in the internal read buffer.
+    ///
+    /// The chunk size is grown to whole packets that fit the buffered data if needed.
+    fn restore_read_chunk_size(&mut self, size: u32) -> Result<(), FtdiError> {
+        let leftover = unsafe {
+            let ctx = &*self.0;
+            slice::from_raw_parts(
+                ctx.readbuffer.add(ctx.readbuffer_offset as usize),
+                ctx.readbuffer_remaining as usize,
+            )
+            .to_vec()
+        };
+        let packet_size = self.packet_size();
+        let needed = leftover.len().div_ceil(packet_size) * packet_size;
+        self.set_read_chunk_size(size.max(needed as u32))?;
+        // Changing the chunk size discards the buffered data, so put it back
+        unsafe {
+            let ctx = &mut *self.0;
+            ptr::copy_nonoverlapping(leftover.as_ptr(), ctx.readbuffer, leftover.len());
+            ctx.readbuffer_offset = 0;
+            ctx.readbuffer_remaining = leftover.len() as u32;
+        }
+        Ok(())
+    }
+    /// Reads data until `event` is received, appends it to `buf` including the event byte, and
+    /// returns the number of bytes appended.
+    ///
+    /// `event` is set as the special event character first, and stays enabled afterwards. The
+    /// chip normally sends received data to the host only when its buffer fills up or the
+    /// latency timer expires, but the event character makes it send its buffer immediately, so
+    /// line-based protocols see each line without waiting for the latency timer.
+    ///
+    /// Bytes received after the event byte are kept in the internal read buffer for the next
+    /// read. Fails with `FtdiError::Timeout` if the event byte doesn't arrive within
+    /// `timeout`, even if other data keeps arriving, in which case the bytes read so far are
+    /// still appended to `buf`.
+    pub fn read_until_event_char(
+        &mut self,
+        event: u8,
+        buf: &mut Vec<u8>,
+        timeout: Duration,
+    ) -> Result<usize, FtdiError> {
+        let deadline = Instant::now() + timeout;
+        self.set_event_char(event, true)?;
+        let start_len = buf.len();
+        let mut byte = [0];
+        loop {
+            // libftdi1 keeps the rest of each USB transfer buffered, so reading a byte at a
+            // time doesn't lose data or cost a USB transfer per byte
+            if self.read_data(&mut byte)? == 1 {
+                buf.push(byte[0]);
+                if byte[0] == event {
+                    return Ok(buf.len() - start_len);
+                }
+            }
+            // Checked after every byte too, so a stream without the event byte can't keep
+            // this going forever
+            if Instant::now() >= deadline {
+                return Err(FtdiError::Timeout);
+            }
+        }
+    }
+    /// Writes all of `tx`, then fills all of `rx`, and returns the number of bytes read.
+    ///
+    /// Taking `&mut self` for the whole transaction keeps other users of the context from
+    /// interleaving their own reads or writes. Note that no MPSSE send immediate command is
+    /// added: include it at the end of `tx` if the response should be sent without waiting for
+    /// the latency timer.
+    pub fn write_then_read(&mut self, tx: &[u8], rx: &mut [u8]) -> Result<usize, FtdiError> {
+        self.write_all(tx)?;
+        self.read_exact(rx)?;
+        Ok(rx.len())
+    }
+    /// Reads exactly `buf.len()` bytes, failing with `FtdiError::Timeout` if the deadline
+    /// passes first.
+    ///
+    /// The USB read timeout is temporarily shortened to the remaining time, so a single read
+    /// can't block past the deadline, and fails with `FtdiError::Timeout` if it times out. On
+    /// timeout, the contents of `buf` are unspecified.
+    pub fn read_exact_timeout(
+        &mut self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> Result<(), FtdiError> {
+        let previous = self.read_timeout();
+        let mut pos = 0;
+        let result = loop {
+            if pos == buf.len() {
+                break Ok(());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break Err(FtdiError::Timeout);
+            }
+            let remaining = (deadline - now).as_nanos().div_ceil(1_000_000);
+            self.set_read_timeout(remaining.min(i32::MAX as u128) as i32);
+            match self.read_data(&mut buf[pos..]) {
+                Ok(len) => pos += len,
+                Err(err) => break Err(err),
+            }
+        };
+        self.set_read_timeout(previous);
+        result
+    }
 }
 
-impl Drop for Context {
-    fn drop(&mut self) {
-        unsafe {
-            sys::ftdi_deinit(self.0.borrow_mut());
+impl Device {
+    /// Reads the whole EEPROM into the internal EEPROM buffer.
+    ///
+    /// This must be called before `eeprom_get_buf` returns valid data.
+    pub fn read_eeprom(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_read_eeprom(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Gets a setting from the internal EEPROM structure
+    pub fn get_eeprom_value(&mut self, value: EepromValue) -> Result<i32, FtdiError> {
+        let mut result = 0;
+        match unsafe { sys::ftdi_get_eeprom_value(self.0, value as u32, &mut result) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(result),
+        }
+    }
+    /// Sets a setting in the internal EEPROM structure.
+    ///
+    /// The change is committed into the internal EEPROM buffer only by a subsequent
+    /// `eeprom_build`.
+    pub fn set_eeprom_value(&mut self, value: EepromValue, data: i32) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_set_eeprom_value(self.0, value as u32, data) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Returns the EEPROM size (in bytes).
+    ///
+    /// The size is known after `read_eeprom` has detected it or `eeprom_init_defaults` has set
+    /// it, and is an error before that or if the EEPROM is blank.
+    pub fn eeprom_size(&mut self) -> Result<usize, FtdiError> {
+        match self.get_eeprom_value(EepromValue::ChipSize)? {
+            size if size > 0 => Ok(size as usize),
+            _ => Err(FtdiError::Other(-1, "EEPROM size unknown")),
+        }
+    }
+    /// Copies the internal EEPROM buffer.
+    ///
+    /// The buffer length is the EEPROM size detected by `read_eeprom`. An empty buffer is
+    /// returned if the size is unknown, e.g. if the EEPROM is blank.
+    pub fn eeprom_get_buf(&mut self) -> Result<Vec<u8>, FtdiError> {
+        let size = self.get_eeprom_value(EepromValue::ChipSize)?;
+        let mut buf = vec![0; size.max(0) as usize];
+        match unsafe { sys::ftdi_get_eeprom_buf(self.0, buf.as_mut_ptr(), buf.len() as c_int) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(buf),
+        }
+    }
+    /// Replaces the internal EEPROM buffer, e.g. with an image edited externally.
+    ///
+    /// The image length must match the EEPROM size detected by `read_eeprom`. Use
+    /// `eeprom_decode` afterwards to update the EEPROM values from the new image.
+    pub fn eeprom_set_buf(&mut self, buf: &[u8]) -> Result<(), FtdiError> {
+        if buf.len() != self.eeprom_size()? {
+            return Err(FtdiError::InvalidArgument(
+                "image length doesn't match the EEPROM size",
+            ));
+        }
+        match unsafe { sys::ftdi_set_eeprom_buf(self.0, buf.as_ptr(), buf.len() as c_int) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Reads the EEPROM, and writes its raw image to `w`
+    pub fn eeprom_dump_to<W: io::Write>(&mut self, mut w: W) -> io::Result<()> {
+        self.read_eeprom()?;
+        let buf = self.eeprom_get_buf()?;
+        w.write_all(&buf)
+    }
+    /// Reads a raw EEPROM image from `r` into the internal EEPROM buffer.
+    ///
+    /// The EEPROM is read first to detect its size, and the image length must match it.
+    /// libftdi1 can't detect the size of a blank EEPROM, so loading an image for one fails with
+    /// `FtdiError::InvalidArgument`. The EEPROM itself is only changed by a subsequent
+    /// `write_eeprom`.
+    pub fn eeprom_load_from<R: io::Read>(&mut self, mut r: R) -> io::Result<()> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        self.read_eeprom()?;
+        let size = match self.get_eeprom_value(EepromValue::ChipSize)? {
+            size if size > 0 => size as usize,
+            _ => {
+                return Err(FtdiError::InvalidArgument(
+                    "EEPROM size unknown, e.g. because the EEPROM is blank",
+                )
+                .into())
+            }
+        };
+        if buf.len() != size {
+            return Err(
+                FtdiError::InvalidArgument("image length doesn't match the EEPROM size").into(),
+            );
+        }
+        match unsafe { sys::ftdi_set_eeprom_buf(self.0, buf.as_ptr(), buf.len() as c_int) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut())).into()),
+            _ => Ok(()),
+        }
+    }
+    /// Writes the internal EEPROM buffer to the EEPROM.
+    ///
+    /// **Warning**: writing a bad image can brick the device, so back up the existing contents
+    /// with `read_eeprom` and `eeprom_get_buf` first.
+    pub fn write_eeprom(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_write_eeprom(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Reads a single 16-bit word from the given EEPROM word address
+    pub fn read_eeprom_location(&mut self, addr: i32) -> Result<u16, FtdiError> {
+        let mut result = 0;
+        match unsafe { sys::ftdi_read_eeprom_location(self.0, addr, &mut result) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(result),
         }
     }
+    /// Writes a single 16-bit word to the given EEPROM word address.
+    ///
+    /// libftdi1 refuses writes to the checksum-protected area, so this is mainly useful for the
+    /// user area.
+    pub fn write_eeprom_location(&mut self, addr: i32, value: u16) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_write_eeprom_location(self.0, addr, value) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Initializes the internal EEPROM structure with default values for the opened chip.
+    ///
+    /// `None` strings are replaced with libftdi1 defaults.
+    pub fn eeprom_init_defaults(
+        &mut self,
+        manufacturer: Option<&str>,
+        product: Option<&str>,
+        serial: Option<&str>,
+    ) -> Result<(), FtdiError> {
+        let manufacturer = to_c_string(manufacturer)?;
+        let product = to_c_string(product)?;
+        let serial = to_c_string(serial)?;
+        match unsafe {
+            sys::ftdi_eeprom_initdefaults(
+                self.0,
+                c_string_ptr(&manufacturer) as *mut c_char,
+                c_string_ptr(&product) as *mut c_char,
+                c_string_ptr(&serial) as *mut c_char,
+            )
+        } {
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Decodes the internal EEPROM buffer into the internal EEPROM structure.
+    ///
+    /// A typical EEPROM modification goes like this:
+    ///
+    /// 1. `read_eeprom` reads the EEPROM into the buffer
+    /// 2. `eeprom_decode` decodes the buffer
+    /// 3. individual settings are modified, e.g. with `eeprom_set_strings`
+    /// 4. `eeprom_build` encodes the structure back into the buffer
+    /// 5. `write_eeprom` writes the buffer to the EEPROM
+    ///
+    /// If `verbose` is true, libftdi1 prints the decoded values to stdout.
+    pub fn eeprom_decode(&mut self, verbose: bool) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_eeprom_decode(self.0, verbose as c_int) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Encodes the internal EEPROM structure into the internal EEPROM buffer, and returns the
+    /// size of the remaining user area (in bytes)
+    pub fn eeprom_build(&mut self) -> Result<usize, FtdiError> {
+        match unsafe { sys::ftdi_eeprom_build(self.0) } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            size => Ok(size as usize),
+        }
+    }
+    /// Verifies the checksum of the image in the internal EEPROM buffer.
+    ///
+    /// The buffer is checked as is, e.g. after `read_eeprom` or `eeprom_set_buf`, without
+    /// rebuilding it. A checksum mismatch is reported through `EepromChecksum::is_valid`.
+    pub fn eeprom_checksum(&mut self) -> Result<EepromChecksum, FtdiError> {
+        let image = self.eeprom_get_buf()?;
+        if image.len() < 2 {
+            return Err(FtdiError::Other(-1, "EEPROM size unknown"));
+        }
+        let stored = u16::from_le_bytes([image[image.len() - 2], image[image.len() - 1]]);
+        Ok(EepromChecksum {
+            stored,
+            computed: compute_eeprom_checksum(self.chip_type(), &image),
+        })
+    }
+    /// Sets the data that `eeprom_build` writes to the EEPROM user area.
+    ///
+    /// The user area is the space left over after the configuration and the strings, so its
+    /// size depends on the EEPROM size and string lengths: the FT232R and FT-X series have a
+    /// fixed internal EEPROM, while other chips use an external 93C46/93C56/93C66 EEPROM (128,
+    /// 256 or 512 bytes). Call `eeprom_build` without user data to find out how much space is
+    /// available.
+    ///
+    /// The data is copied, so the slice doesn't need to outlive this call.
+    pub fn eeprom_set_user_data(&mut self, data: &[u8]) -> Result<(), FtdiError> {
+        // libftdi1 keeps a pointer to the data until the EEPROM is built
+        let data = data.to_vec();
+        match unsafe {
+            sys::ftdi_set_eeprom_user_data(
+                self.0,
+                data.as_ptr() as *const c_char,
+                data.len() as c_int,
+            )
+        } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => {
+                self.1.eeprom_user_data = data;
+                Ok(())
+            }
+        }
+    }
+    /// Returns the manufacturer, product and serial number strings stored in the internal EEPROM
+    /// structure.
+    ///
+    /// The strings must have been set first, e.g. with `eeprom_decode` or `eeprom_set_strings`.
+    pub fn eeprom_get_strings(&mut self) -> Result<DeviceStrings, FtdiError> {
+        let mut manufacturer = [0; 128];
+        let mut product = [0; 128];
+        let mut serial = [0; 128];
+        match unsafe {
+            sys::ftdi_eeprom_get_strings(
+                self.0,
+                manufacturer.as_mut_ptr(),
+                manufacturer.len() as c_int,
+                product.as_mut_ptr(),
+                product.len() as c_int,
+                serial.as_mut_ptr(),
+                serial.len() as c_int,
+            )
+        } {
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(DeviceStrings {
+                manufacturer: buf_string(&manufacturer),
+                product: buf_string(&product),
+                serial: buf_string(&serial),
+            }),
+        }
+    }
+    /// Sets the manufacturer, product and serial number strings in the internal EEPROM
+    /// structure.
+    ///
+    /// `None` leaves the corresponding string unchanged. The changes are committed into the
+    /// internal EEPROM buffer only by a subsequent `eeprom_build`.
+    pub fn eeprom_set_strings(
+        &mut self,
+        manufacturer: Option<&str>,
+        product: Option<&str>,
+        serial: Option<&str>,
+    ) -> Result<(), FtdiError> {
+        let manufacturer = to_c_string(manufacturer)?;
+        let product = to_c_string(product)?;
+        let serial = to_c_string(serial)?;
+        match unsafe {
+            sys::ftdi_eeprom_set_strings(
+                self.0,
+                c_string_ptr(&manufacturer) as *mut c_char,
+                c_string_ptr(&product) as *mut c_char,
+                c_string_ptr(&serial) as *mut c_char,
+            )
+        } {
+            -3 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+    /// Erases the EEPROM.
+    ///
+    /// **Warning**: the device uses default settings until a valid image is written again, so
+    /// back up the existing contents with `read_eeprom` and `eeprom_get_buf` first.
+    pub fn erase_eeprom(&mut self) -> Result<(), FtdiError> {
+        match unsafe { sys::ftdi_erase_eeprom(self.0) } {
+            -2 => Err(FtdiError::UsbDeviceUnavailable),
+            code if code < 0 => Err(FtdiError::Other(code, error_msg(self.raw_mut()))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Device {
+    /// Starts writing data asynchronously.
+    ///
+    /// Several writes can be in progress at the same time.
+    ///
+    /// # Safety
+    ///
+    /// libusb reads `data` until the transfer is finished, which is only guaranteed if the
+    /// returned `TransferControl` is waited for, cancelled or dropped. The caller must not leak
+    /// it, e.g. with `mem::forget`, or libusb may read `data` after the borrow has ended.
+    pub unsafe fn submit_write<'a>(
+        &'a self,
+        data: &'a [u8],
+    ) -> Result<TransferControl<'a>, FtdiError> {
+        // libftdi1 doesn't check for a closed handle before submitting the transfer
+        self.usb_dev()?;
+        let tc = sys::ftdi_write_data_submit(self.0, data.as_ptr() as *mut _, data.len() as c_int);
+        transfer_control(tc)
+    }
+    /// Starts reading data asynchronously.
+    ///
+    /// The buffer stays borrowed until the transfer is finished or cancelled, so its contents
+    /// can't be accessed while the transfer is in progress.
+    ///
+    /// # Safety
+    ///
+    /// libusb writes into `buf` until the transfer is finished, which is only guaranteed if the
+    /// returned `TransferControl` is waited for, cancelled or dropped. The caller must not leak
+    /// it, e.g. with `mem::forget`, or libusb may write into `buf` after the borrow has ended.
+    pub unsafe fn submit_read<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> Result<TransferControl<'a>, FtdiError> {
+        self.usb_dev()?;
+        let tc = sys::ftdi_read_data_submit(self.0, buf.as_mut_ptr(), buf.len() as c_int);
+        transfer_control(tc)
+    }
+}
+
+impl Device {
+    /// Reads a continuous stream of data in synchronous FIFO mode.
+    ///
+    /// The callback is called with each received chunk of data, and returns `false` to stop
+    /// streaming. Only FT2232H and FT232H chips support this mode. A panic in the callback stops
+    /// streaming and is resumed once libftdi1 has returned.
+    pub fn read_stream<F>(
+        &mut self,
+        callback: F,
+        packets_per_transfer: u32,
+        num_transfers: u32,
+    ) -> Result<(), FtdiError>
+    where
+        F: FnMut(&[u8]) -> bool,
+    {
+        let mut state = StreamState {
+            callback,
+            stopped: false,
+            panic: None,
+        };
+        let code = unsafe {
+            sys::ftdi_readstream(
+                self.0,
+                Some(stream_callback::<F>),
+                &mut state as *mut StreamState<F> as *mut c_void,
+                packets_per_transfer as c_int,
+                num_transfers as c_int,
+            )
+        };
+        if let Some(payload) = state.panic {
+            panic::resume_unwind(payload);
+        }
+        match code {
+            0 => Ok(()),
+            _ if state.stopped => Ok(()),
+            code => Err(FtdiError::Other(code, "stream reading failed")),
+        }
+    }
+}
+
+struct StreamState<F> {
+    callback: F,
+    stopped: bool,
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn stream_callback<F>(
+    buffer: *mut u8,
+    length: c_int,
+    _progress: *mut sys::FTDIProgressInfo,
+    userdata: *mut c_void,
+) -> c_int
+where
+    F: FnMut(&[u8]) -> bool,
+{
+    let state = &mut *(userdata as *mut StreamState<F>);
+    if state.stopped {
+        return 1;
+    }
+    // Progress updates are reported without any data
+    if buffer.is_null() || length <= 0 {
+        return 0;
+    }
+    let data = slice::from_raw_parts(buffer, length as usize);
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.callback)(data))) {
+        Ok(true) => 0,
+        Ok(false) => {
+            state.stopped = true;
+            1
+        }
+        Err(payload) => {
+            state.panic = Some(payload);
+            state.stopped = true;
+            1
+        }
+    }
+}
+
+fn transfer_control<'a>(
+    tc: *mut sys::ftdi_transfer_control,
+) -> Result<TransferControl<'a>, FtdiError> {
+    if tc.is_null() {
+        Err(FtdiError::Other(-1, "failed to submit USB transfer"))
+    } else {
+        Ok(TransferControl {
+            tc,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Reads data with `read_data`, waiting at most the USB read timeout for at least one byte.
+///
+/// Returns an `io::ErrorKind::TimedOut` error if no data arrives in time, so generic `io::Read`
+/// consumers like `read_exact` fail instead of waiting forever or seeing end of file.
+impl io::Read for Device {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let deadline = Instant::now() + Duration::from_millis(self.read_timeout().max(0) as u64);
+        loop {
+            let len = self.read_data(buf)?;
+            if len > 0 {
+                return Ok(len);
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for data",
+                ));
+            }
+        }
+    }
+}
+
+/// Writes data with `write_data`.
+///
+/// `flush` is a no-op, because `write_data` returns only after all data has been transferred
+/// to the chip.
+impl io::Write for Device {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_data(buf)?)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Closes the device if one is open, and frees the context once the `DeviceInfo`s it found
+/// have been dropped too.
+///
+/// Closing releases the claimed USB interface, so the device can be opened again right away,
+/// e.g. by another process. Errors from closing can't be reported here; call
+/// `Device::usb_close` explicitly to handle them.
+impl<S> Drop for Context<S> {
+    fn drop(&mut self) {
+        // The raw context is freed by `RawContext`
+        let _ = self.close_device();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_errors_have_codes() {
+        assert_eq!(FtdiError::DeviceNotFound.code(), Some(-3));
+        assert_eq!(FtdiError::ClaimInterfaceFailed.code(), Some(-5));
+        assert_eq!(FtdiError::Timeout.code(), Some(-7));
+        assert_eq!(FtdiError::InvalidArgument("test").code(), None);
+    }
+
+    #[test]
+    fn close_device_twice() {
+        let mut ctx = Context::new().unwrap();
+        assert_eq!(ctx.close_device(), Ok(()));
+        assert_eq!(ctx.close_device(), Ok(()));
+    }
+
+    #[test]
+    fn library_version() {
+        let version = Context::get_library_version();
+        assert!(version.major >= 1);
+        assert!(!version.version_str.is_empty());
+    }
+
+    #[test]
+    fn latency_timer_zero_is_rejected() {
+        assert!(matches!(
+            check_latency_timer(0),
+            Err(FtdiError::InvalidArgument(_))
+        ));
+        assert_eq!(check_latency_timer(1), Ok(()));
+    }
+
+    #[test]
+    #[ignore = "requires an FTDI device with the default vendor and product id"]
+    fn usb_open_dev_found_device() {
+        let mut ctx = Context::new().unwrap();
+        let dev = ctx.find_all(0, 0).unwrap().into_iter().next().unwrap();
+        let device = ctx.usb_open_dev(&dev).unwrap();
+        device.usb_close().unwrap();
+    }
+
+    #[test]
+    #[ignore = "requires an FTDI device with the default vendor and product id"]
+    fn submit_read_cancel() {
+        let ctx = Context::new().unwrap();
+        let mut device = ctx.usb_open(0x0403, 0x6001).unwrap();
+        let mut buf = [0; 64];
+        let tc = unsafe { device.submit_read(&mut buf) }.unwrap();
+        tc.cancel(Duration::from_secs(1));
+        device.usb_close().unwrap();
+    }
 }
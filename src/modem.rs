@@ -0,0 +1,61 @@
+use crate::{Device, FtdiError, ModemStatus};
+
+/// Modem status flags that changed between two polls
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ModemChange {
+    /// The new status
+    pub status: ModemStatus,
+    /// Flags that were set since the previous poll
+    pub turned_on: ModemStatus,
+    /// Flags that were cleared since the previous poll
+    pub turned_off: ModemStatus,
+}
+
+impl ModemChange {
+    /// Returns all flags that changed
+    pub fn changed(&self) -> ModemStatus {
+        self.turned_on | self.turned_off
+    }
+    /// Returns true if no flags changed
+    pub fn is_empty(&self) -> bool {
+        self.changed().is_empty()
+    }
+}
+
+/// Tracks the modem status across polls to detect transitions.
+///
+/// The first poll compares against an empty status, so all flags that are set are reported as
+/// turned on.
+#[derive(Clone, Debug)]
+pub struct ModemMonitor {
+    last: ModemStatus,
+}
+
+impl Default for ModemMonitor {
+    fn default() -> ModemMonitor {
+        ModemMonitor::new()
+    }
+}
+
+impl ModemMonitor {
+    pub fn new() -> ModemMonitor {
+        ModemMonitor {
+            last: ModemStatus::empty(),
+        }
+    }
+    /// Polls the modem status, and returns the changes since the previous poll
+    pub fn poll(&mut self, ctx: &mut Device) -> Result<ModemChange, FtdiError> {
+        let status = ctx.poll_modem_status()?;
+        let change = ModemChange {
+            status,
+            turned_on: status - self.last,
+            turned_off: self.last - status,
+        };
+        self.last = status;
+        Ok(change)
+    }
+    /// Returns the status seen by the previous poll
+    pub fn last(&self) -> ModemStatus {
+        self.last
+    }
+}
@@ -0,0 +1,246 @@
+use std::time::{Duration, Instant};
+
+use crate::{Device, FtdiError};
+
+/// Clock bytes out on the falling edge, MSB first
+pub(crate) const CLOCK_BYTES_OUT: u8 = 0x11;
+/// Clock bytes in on the rising edge, MSB first
+pub(crate) const CLOCK_BYTES_IN: u8 = 0x20;
+/// Clock bytes out on the falling edge and in on the rising edge, MSB first
+pub(crate) const CLOCK_BYTES_IN_OUT: u8 = 0x31;
+/// Set the low byte GPIO values and directions
+pub(crate) const SET_GPIO_LOW: u8 = 0x80;
+/// Read the low byte GPIO values
+pub(crate) const READ_GPIO_LOW: u8 = 0x81;
+/// Set the high byte GPIO values and directions
+pub(crate) const SET_GPIO_HIGH: u8 = 0x82;
+/// Read the high byte GPIO values
+pub(crate) const READ_GPIO_HIGH: u8 = 0x83;
+/// Connect TDI/DO to TDO/DI for loopback testing
+pub(crate) const LOOPBACK_ENABLE: u8 = 0x84;
+/// Disconnect TDI/DO from TDO/DI
+pub(crate) const LOOPBACK_DISABLE: u8 = 0x85;
+/// Set the clock divisor
+pub(crate) const SET_CLOCK_DIVISOR: u8 = 0x86;
+/// Flush the chip's buffer back to the host
+pub(crate) const SEND_IMMEDIATE: u8 = 0x87;
+/// Use the 60 MHz master clock directly (H-type chips only)
+pub(crate) const DISABLE_CLOCK_DIVIDE_BY_5: u8 = 0x8a;
+/// Divide the 60 MHz master clock by 5 (H-type chips only)
+pub(crate) const ENABLE_CLOCK_DIVIDE_BY_5: u8 = 0x8b;
+
+/// Maximum number of bytes a single MPSSE clock command can transfer
+const MAX_CLOCK_LEN: usize = 65536;
+
+/// Builder for a sequence of MPSSE commands.
+///
+/// Commands are accumulated into a buffer, and written to the chip with a single `send` call.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[must_use = "commands are only written to the chip by `send`"]
+pub struct MpsseBuilder {
+    buf: Vec<u8>,
+}
+
+impl MpsseBuilder {
+    pub fn new() -> MpsseBuilder {
+        MpsseBuilder::default()
+    }
+    /// Clocks bytes out on the falling edge, MSB first (opcode `0x11`)
+    pub fn clock_bytes_out(mut self, data: &[u8]) -> MpsseBuilder {
+        for chunk in data.chunks(MAX_CLOCK_LEN) {
+            self.clock_command(CLOCK_BYTES_OUT, chunk.len());
+            self.buf.extend_from_slice(chunk);
+        }
+        self
+    }
+    /// Clocks `len` bytes in on the rising edge, MSB first (opcode `0x20`)
+    pub fn clock_bytes_in(mut self, len: usize) -> MpsseBuilder {
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk_len = remaining.min(MAX_CLOCK_LEN);
+            self.clock_command(CLOCK_BYTES_IN, chunk_len);
+            remaining -= chunk_len;
+        }
+        self
+    }
+    /// Clocks bytes out on the falling edge and in on the rising edge, MSB first (opcode `0x31`)
+    pub fn clock_bytes_in_out(mut self, data: &[u8]) -> MpsseBuilder {
+        for chunk in data.chunks(MAX_CLOCK_LEN) {
+            self.clock_command(CLOCK_BYTES_IN_OUT, chunk.len());
+            self.buf.extend_from_slice(chunk);
+        }
+        self
+    }
+    /// Sets the values and directions of the low byte GPIO pins (opcode `0x80`).
+    ///
+    /// A direction bit set to 1 makes the pin an output.
+    pub fn set_gpio_low(mut self, value: u8, direction: u8) -> MpsseBuilder {
+        self.buf
+            .extend_from_slice(&[SET_GPIO_LOW, value, direction]);
+        self
+    }
+    /// Sets the values and directions of the high byte GPIO pins (opcode `0x82`).
+    ///
+    /// A direction bit set to 1 makes the pin an output.
+    pub fn set_gpio_high(mut self, value: u8, direction: u8) -> MpsseBuilder {
+        self.buf
+            .extend_from_slice(&[SET_GPIO_HIGH, value, direction]);
+        self
+    }
+    /// Reads the low byte GPIO pins, producing one byte of response (opcode `0x81`)
+    pub fn read_gpio_low(mut self) -> MpsseBuilder {
+        self.buf.push(READ_GPIO_LOW);
+        self
+    }
+    /// Reads the high byte GPIO pins, producing one byte of response (opcode `0x83`)
+    pub fn read_gpio_high(mut self) -> MpsseBuilder {
+        self.buf.push(READ_GPIO_HIGH);
+        self
+    }
+    /// Sets the clock divisor (opcode `0x86`)
+    pub fn set_clock_divisor(mut self, divisor: u16) -> MpsseBuilder {
+        self.buf
+            .extend_from_slice(&[SET_CLOCK_DIVISOR, divisor as u8, (divisor >> 8) as u8]);
+        self
+    }
+    /// Uses the 60 MHz master clock directly on H-type chips (opcode `0x8A`)
+    pub fn disable_clock_divide_by_5(mut self) -> MpsseBuilder {
+        self.buf.push(DISABLE_CLOCK_DIVIDE_BY_5);
+        self
+    }
+    /// Divides the 60 MHz master clock by 5 on H-type chips (opcode `0x8B`)
+    pub fn enable_clock_divide_by_5(mut self) -> MpsseBuilder {
+        self.buf.push(ENABLE_CLOCK_DIVIDE_BY_5);
+        self
+    }
+    /// Connects TDI/DO to TDO/DI internally (opcode `0x84`)
+    pub fn enable_loopback(mut self) -> MpsseBuilder {
+        self.buf.push(LOOPBACK_ENABLE);
+        self
+    }
+    /// Disconnects TDI/DO from TDO/DI (opcode `0x85`)
+    pub fn disable_loopback(mut self) -> MpsseBuilder {
+        self.buf.push(LOOPBACK_DISABLE);
+        self
+    }
+    /// Makes the chip send its buffered data back to the host immediately (opcode `0x87`)
+    pub fn send_immediate(mut self) -> MpsseBuilder {
+        self.buf.push(SEND_IMMEDIATE);
+        self
+    }
+    /// Returns the accumulated command bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+    /// Writes the accumulated commands to the chip
+    pub fn send(self, ctx: &mut Device) -> Result<(), FtdiError> {
+        ctx.write_all(&self.buf)
+    }
+    fn clock_command(&mut self, opcode: u8, len: usize) {
+        let len = (len - 1) as u16;
+        self.buf
+            .extend_from_slice(&[opcode, len as u8, (len >> 8) as u8]);
+    }
+}
+
+impl Device {
+    /// Sets the values and directions of both MPSSE GPIO bytes in one write.
+    ///
+    /// The low byte controls ADBUS0-7 (BDBUS0-7 on interface B), and the high byte ACBUS0-7
+    /// (BCBUS0-7 on interface B). Bit `n` of each byte corresponds to pin `n`, and a direction
+    /// bit set to 1 makes the pin an output.
+    pub fn mpsse_set_gpio(
+        &mut self,
+        low_value: u8,
+        low_direction: u8,
+        high_value: u8,
+        high_direction: u8,
+    ) -> Result<(), FtdiError> {
+        MpsseBuilder::new()
+            .set_gpio_low(low_value, low_direction)
+            .set_gpio_high(high_value, high_direction)
+            .send(self)
+    }
+    /// Reads both MPSSE GPIO bytes.
+    ///
+    /// Bits 0-7 of the result are ADBUS0-7 and bits 8-15 are ACBUS0-7 (BDBUS/BCBUS on
+    /// interface B).
+    pub fn mpsse_read_gpio(&mut self) -> Result<u16, FtdiError> {
+        MpsseBuilder::new()
+            .read_gpio_low()
+            .read_gpio_high()
+            .send_immediate()
+            .send(self)?;
+        let mut buf = [0; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+    /// Makes the chip send its buffered response data back to the host immediately.
+    ///
+    /// The MPSSE holds responses to read commands in its buffer until it fills up or the
+    /// latency timer expires. Sending this after a read command makes the response available
+    /// right away, so a following `read_data` doesn't have to wait for it.
+    pub fn mpsse_send_immediate(&mut self) -> Result<(), FtdiError> {
+        MpsseBuilder::new().send_immediate().send(self)
+    }
+    /// Runs an MPSSE loopback test, and returns true if `pattern` was read back unchanged.
+    ///
+    /// Loopback is enabled for the duration of the test, and disabled again afterwards. The
+    /// interface must be in MPSSE mode with a suitable clock configured. If the data doesn't
+    /// come back within the USB read timeout, the test fails with `Ok(false)` rather than an
+    /// error.
+    pub fn mpsse_loopback_test(&mut self, pattern: &[u8]) -> Result<bool, FtdiError> {
+        MpsseBuilder::new()
+            .enable_loopback()
+            .clock_bytes_in_out(pattern)
+            .send_immediate()
+            .send(self)?;
+        let mut buf = vec![0; pattern.len()];
+        let timeout = Duration::from_millis(self.read_timeout().max(0) as u64);
+        let result = self.read_exact_timeout(&mut buf, Instant::now() + timeout);
+        MpsseBuilder::new().disable_loopback().send(self)?;
+        match result {
+            Ok(()) => Ok(buf == pattern),
+            // USB read timeouts end up here too, since `read_data` reports them as timeouts
+            Err(FtdiError::Timeout) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+    /// Sets the MPSSE clock frequency as close to `hz` as possible, and returns the actual
+    /// frequency.
+    ///
+    /// The actual frequency doesn't exceed `hz` unless `hz` is below the lowest supported
+    /// frequency.
+    ///
+    /// H-type chips use a 60 MHz base clock (or 12 MHz for very low frequencies), and other
+    /// chips a 12 MHz base clock. The clock frequency is `base / ((1 + divisor) * 2)`.
+    pub fn set_mpsse_clock(&mut self, hz: u32) -> Result<u32, FtdiError> {
+        if hz == 0 {
+            return Err(FtdiError::InvalidArgument(
+                "MPSSE clock frequency must not be zero",
+            ));
+        }
+        let high_speed = self.chip_type().is_high_speed();
+        let mut builder = MpsseBuilder::new();
+        let mut base = 12_000_000;
+        if high_speed {
+            // 60 MHz can't reach frequencies below ~458 Hz even with the maximum divisor
+            if hz >= clock_frequency(60_000_000, 0xffff) {
+                base = 60_000_000;
+                builder = builder.disable_clock_divide_by_5();
+            } else {
+                builder = builder.enable_clock_divide_by_5();
+            }
+        }
+        let divisor = u64::from(base)
+            .div_ceil(2 * u64::from(hz))
+            .saturating_sub(1)
+            .min(0xffff) as u32;
+        builder.set_clock_divisor(divisor as u16).send(self)?;
+        Ok(clock_frequency(base, divisor))
+    }
+}
+
+fn clock_frequency(base: u32, divisor: u32) -> u32 {
+    base / ((1 + divisor) * 2)
+}
@@ -0,0 +1,244 @@
+use serialport::{ClearBuffer, ErrorKind, SerialPort};
+use std::cell::RefCell;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Bits, Device, FlowControl, FtdiError, Parity, StopBits};
+
+/// `serialport::SerialPort` implementation over a device.
+///
+/// libftdi1 can't query the line settings from the chip, so the wrapper remembers the settings
+/// it has applied. The serialport timeout is used for both the USB read and write timeouts.
+#[derive(Debug)]
+pub struct FtdiSerialPort {
+    ctx: RefCell<Device>,
+    baud_rate: u32,
+    data_bits: serialport::DataBits,
+    parity: serialport::Parity,
+    stop_bits: serialport::StopBits,
+    flow_control: serialport::FlowControl,
+    timeout: Duration,
+}
+
+impl FtdiSerialPort {
+    /// Configures a device for the given baud rate, 8 data bits, no parity, one stop
+    /// bit, and no flow control
+    pub fn new(mut ctx: Device, baud_rate: u32) -> Result<FtdiSerialPort, FtdiError> {
+        ctx.set_baud_rate(baud_rate)?;
+        ctx.set_line_property(Bits::Eight, StopBits::One, Parity::None)?;
+        ctx.set_flow_control(FlowControl::None)?;
+        let timeout = Duration::from_millis(ctx.read_timeout().max(0) as u64);
+        Ok(FtdiSerialPort {
+            ctx: RefCell::new(ctx),
+            baud_rate,
+            data_bits: serialport::DataBits::Eight,
+            parity: serialport::Parity::None,
+            stop_bits: serialport::StopBits::One,
+            flow_control: serialport::FlowControl::None,
+            timeout,
+        })
+    }
+    /// Returns the underlying device
+    pub fn into_inner(self) -> Device {
+        self.ctx.into_inner()
+    }
+    fn set_line_property(
+        &self,
+        data_bits: serialport::DataBits,
+        parity: serialport::Parity,
+        stop_bits: serialport::StopBits,
+        break_on: bool,
+    ) -> serialport::Result<()> {
+        let bits = match data_bits {
+            serialport::DataBits::Seven => Bits::Seven,
+            serialport::DataBits::Eight => Bits::Eight,
+            serialport::DataBits::Five | serialport::DataBits::Six => {
+                return Err(serialport::Error::new(
+                    ErrorKind::InvalidInput,
+                    "only 7 and 8 data bits are supported",
+                ))
+            }
+        };
+        let parity = match parity {
+            serialport::Parity::None => Parity::None,
+            serialport::Parity::Odd => Parity::Odd,
+            serialport::Parity::Even => Parity::Even,
+        };
+        let stop = match stop_bits {
+            serialport::StopBits::One => StopBits::One,
+            serialport::StopBits::Two => StopBits::Two,
+        };
+        self.ctx
+            .borrow_mut()
+            .set_line_property_break(bits, stop, parity, break_on)?;
+        Ok(())
+    }
+    fn modem_status(&self) -> serialport::Result<crate::ModemStatus> {
+        Ok(self.ctx.borrow_mut().poll_modem_status()?)
+    }
+}
+
+impl From<FtdiError> for serialport::Error {
+    fn from(err: FtdiError) -> serialport::Error {
+        let kind = match err {
+            FtdiError::UsbDeviceUnavailable
+            | FtdiError::DeviceNotFound
+            | FtdiError::AccessDenied
+            | FtdiError::ClaimInterfaceFailed => ErrorKind::NoDevice,
+            FtdiError::InvalidArgument(_) | FtdiError::InvalidBaudRate => ErrorKind::InvalidInput,
+            // -7 is LIBUSB_ERROR_TIMEOUT, which libftdi1 passes through from USB transfers
+            FtdiError::Timeout | FtdiError::Other(-7, _) => ErrorKind::Io(io::ErrorKind::TimedOut),
+            FtdiError::ControlTransferFailed(_) | FtdiError::Other(..) => ErrorKind::Unknown,
+        };
+        serialport::Error::new(kind, err.to_string())
+    }
+}
+
+/// Reads data, waiting at most the configured timeout for at least one byte.
+///
+/// Returns an `io::ErrorKind::TimedOut` error if no data arrives in time, also when the timeout
+/// is shorter than the latency timer and the USB read itself times out.
+impl io::Read for FtdiSerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let len = self.ctx.get_mut().read_data(buf)?;
+            if len > 0 {
+                return Ok(len);
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for data",
+                ));
+            }
+        }
+    }
+}
+
+impl io::Write for FtdiSerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.ctx.get_mut().write_data(buf)?)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for FtdiSerialPort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(self.baud_rate)
+    }
+    fn data_bits(&self) -> serialport::Result<serialport::DataBits> {
+        Ok(self.data_bits)
+    }
+    fn flow_control(&self) -> serialport::Result<serialport::FlowControl> {
+        Ok(self.flow_control)
+    }
+    fn parity(&self) -> serialport::Result<serialport::Parity> {
+        Ok(self.parity)
+    }
+    fn stop_bits(&self) -> serialport::Result<serialport::StopBits> {
+        Ok(self.stop_bits)
+    }
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.ctx.get_mut().set_baud_rate(baud_rate)?;
+        self.baud_rate = baud_rate;
+        Ok(())
+    }
+    fn set_data_bits(&mut self, data_bits: serialport::DataBits) -> serialport::Result<()> {
+        self.set_line_property(data_bits, self.parity, self.stop_bits, false)?;
+        self.data_bits = data_bits;
+        Ok(())
+    }
+    fn set_flow_control(
+        &mut self,
+        flow_control: serialport::FlowControl,
+    ) -> serialport::Result<()> {
+        let value = match flow_control {
+            serialport::FlowControl::None => FlowControl::None,
+            serialport::FlowControl::Software => FlowControl::XonXoff,
+            serialport::FlowControl::Hardware => FlowControl::RtsCts,
+        };
+        self.ctx.get_mut().set_flow_control(value)?;
+        self.flow_control = flow_control;
+        Ok(())
+    }
+    fn set_parity(&mut self, parity: serialport::Parity) -> serialport::Result<()> {
+        self.set_line_property(self.data_bits, parity, self.stop_bits, false)?;
+        self.parity = parity;
+        Ok(())
+    }
+    fn set_stop_bits(&mut self, stop_bits: serialport::StopBits) -> serialport::Result<()> {
+        self.set_line_property(self.data_bits, self.parity, stop_bits, false)?;
+        self.stop_bits = stop_bits;
+        Ok(())
+    }
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        // libusb treats a zero timeout as unlimited
+        let millis = timeout.as_millis().clamp(1, i32::MAX as u128) as i32;
+        let ctx = self.ctx.get_mut();
+        ctx.set_read_timeout(millis);
+        ctx.set_write_timeout(millis);
+        self.timeout = timeout;
+        Ok(())
+    }
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        Ok(self.ctx.get_mut().set_rts(level)?)
+    }
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        Ok(self.ctx.get_mut().set_dtr(level)?)
+    }
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(self.modem_status()?.cts())
+    }
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(self.modem_status()?.dsr())
+    }
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(self.modem_status()?.ring_indicator())
+    }
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(self.modem_status()?.dcd())
+    }
+    /// Returns the number of bytes buffered by libftdi1.
+    ///
+    /// Data still waiting in the chip's own buffer is not included.
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(unsafe { (*self.ctx.borrow().0).readbuffer_remaining })
+    }
+    /// Always returns 0, because writes complete before returning
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        let mut ctx = self.ctx.borrow_mut();
+        match buffer_to_clear {
+            ClearBuffer::Input => ctx.tci_flush()?,
+            ClearBuffer::Output => ctx.tco_flush()?,
+            ClearBuffer::All => ctx.tcio_flush()?,
+        }
+        Ok(())
+    }
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(serialport::Error::new(
+            ErrorKind::Unknown,
+            "cloning an FTDI serial port is not supported",
+        ))
+    }
+    fn set_break(&self) -> serialport::Result<()> {
+        self.set_line_property(self.data_bits, self.parity, self.stop_bits, true)
+    }
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.set_line_property(self.data_bits, self.parity, self.stop_bits, false)
+    }
+}
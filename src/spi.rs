@@ -0,0 +1,72 @@
+use embedded_hal::spi::{self, ErrorKind, ErrorType, SpiBus};
+
+use crate::{BitMode, Device, FtdiError, MpsseBuilder};
+
+/// SPI bus (mode 0, MSB first) over an MPSSE-capable interface.
+///
+/// Uses ADBUS0 as SCK, ADBUS1 as MOSI, and ADBUS2 as MISO. Chip select is not handled by the
+/// bus.
+#[derive(Debug)]
+pub struct MpsseSpi {
+    ctx: Device,
+}
+
+impl MpsseSpi {
+    /// Switches a device to MPSSE mode and configures the SPI pins
+    pub fn new(mut ctx: Device) -> Result<MpsseSpi, FtdiError> {
+        ctx.set_bit_mode(0, BitMode::Reset)?;
+        ctx.set_bit_mode(0, BitMode::Mpsse)?;
+        // SCK and MOSI are outputs, everything else is an input
+        MpsseBuilder::new()
+            .set_gpio_low(0x00, 0x03)
+            .send(&mut ctx)?;
+        Ok(MpsseSpi { ctx })
+    }
+    /// Returns the underlying device
+    pub fn into_inner(self) -> Device {
+        self.ctx
+    }
+}
+
+impl spi::Error for FtdiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl ErrorType for MpsseSpi {
+    type Error = FtdiError;
+}
+
+impl SpiBus<u8> for MpsseSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), FtdiError> {
+        MpsseBuilder::new()
+            .clock_bytes_in(words.len())
+            .send_immediate()
+            .send(&mut self.ctx)?;
+        self.ctx.read_exact(words)
+    }
+    fn write(&mut self, words: &[u8]) -> Result<(), FtdiError> {
+        MpsseBuilder::new()
+            .clock_bytes_out(words)
+            .send(&mut self.ctx)
+    }
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), FtdiError> {
+        let mut buf = vec![0; read.len().max(write.len())];
+        buf[..write.len()].copy_from_slice(write);
+        self.transfer_in_place(&mut buf)?;
+        read.copy_from_slice(&buf[..read.len()]);
+        Ok(())
+    }
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), FtdiError> {
+        MpsseBuilder::new()
+            .clock_bytes_in_out(words)
+            .send_immediate()
+            .send(&mut self.ctx)?;
+        self.ctx.read_exact(words)
+    }
+    fn flush(&mut self) -> Result<(), FtdiError> {
+        // Writes are synchronous, so there is nothing to flush
+        Ok(())
+    }
+}
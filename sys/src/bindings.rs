@@ -48,6 +48,256 @@ fn bindgen_test_layout_timeval() {
         )
     );
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct libusb_device_descriptor {
+    pub bLength: u8,
+    pub bDescriptorType: u8,
+    pub bcdUSB: u16,
+    pub bDeviceClass: u8,
+    pub bDeviceSubClass: u8,
+    pub bDeviceProtocol: u8,
+    pub bMaxPacketSize0: u8,
+    pub idVendor: u16,
+    pub idProduct: u16,
+    pub bcdDevice: u16,
+    pub iManufacturer: u8,
+    pub iProduct: u8,
+    pub iSerialNumber: u8,
+    pub bNumConfigurations: u8,
+}
+#[test]
+fn bindgen_test_layout_libusb_device_descriptor() {
+    assert_eq!(
+        ::std::mem::size_of::<libusb_device_descriptor>(),
+        18usize,
+        concat!("Size of: ", stringify!(libusb_device_descriptor))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<libusb_device_descriptor>(),
+        2usize,
+        concat!("Alignment of ", stringify!(libusb_device_descriptor))
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bLength as *const _ as usize
+        },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bLength)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bDescriptorType as *const _
+                as usize
+        },
+        1usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bDescriptorType)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<libusb_device_descriptor>())).bcdUSB as *const _ as usize },
+        2usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bcdUSB)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bDeviceClass as *const _ as usize
+        },
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bDeviceClass)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bDeviceSubClass as *const _
+                as usize
+        },
+        5usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bDeviceSubClass)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bDeviceProtocol as *const _
+                as usize
+        },
+        6usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bDeviceProtocol)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bMaxPacketSize0 as *const _
+                as usize
+        },
+        7usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bMaxPacketSize0)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).idVendor as *const _ as usize
+        },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(idVendor)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).idProduct as *const _ as usize
+        },
+        10usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(idProduct)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bcdDevice as *const _ as usize
+        },
+        12usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bcdDevice)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).iManufacturer as *const _ as usize
+        },
+        14usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(iManufacturer)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).iProduct as *const _ as usize
+        },
+        15usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(iProduct)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).iSerialNumber as *const _ as usize
+        },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(iSerialNumber)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<libusb_device_descriptor>())).bNumConfigurations as *const _
+                as usize
+        },
+        17usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(libusb_device_descriptor),
+            "::",
+            stringify!(bNumConfigurations)
+        )
+    );
+}
+extern "C" {
+    pub fn libusb_ref_device(dev: *mut libusb_device) -> *mut libusb_device;
+}
+extern "C" {
+    pub fn libusb_unref_device(dev: *mut libusb_device);
+}
+extern "C" {
+    pub fn libusb_get_device(dev_handle: *mut libusb_device_handle) -> *mut libusb_device;
+}
+extern "C" {
+    pub fn libusb_get_device_descriptor(
+        dev: *mut libusb_device,
+        desc: *mut libusb_device_descriptor,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libusb_get_bus_number(dev: *mut libusb_device) -> u8;
+}
+extern "C" {
+    pub fn libusb_get_device_address(dev: *mut libusb_device) -> u8;
+}
+extern "C" {
+    pub fn libusb_open(
+        dev: *mut libusb_device,
+        dev_handle: *mut *mut libusb_device_handle,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libusb_close(dev_handle: *mut libusb_device_handle);
+}
+extern "C" {
+    pub fn libusb_bulk_transfer(
+        dev_handle: *mut libusb_device_handle,
+        endpoint: ::std::os::raw::c_uchar,
+        data: *mut ::std::os::raw::c_uchar,
+        length: ::std::os::raw::c_int,
+        actual_length: *mut ::std::os::raw::c_int,
+        timeout: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libusb_get_string_descriptor_ascii(
+        dev_handle: *mut libusb_device_handle,
+        desc_index: u8,
+        data: *mut ::std::os::raw::c_uchar,
+        length: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
 pub const ftdi_chip_type_TYPE_AM: ftdi_chip_type = 0;
 pub const ftdi_chip_type_TYPE_BM: ftdi_chip_type = 1;
 pub const ftdi_chip_type_TYPE_2232C: ftdi_chip_type = 2;
@@ -91,6 +341,7 @@ pub const ftdi_interface_INTERFACE_D: ftdi_interface = 4;
 pub type ftdi_interface = u32;
 pub const ftdi_module_detach_mode_AUTO_DETACH_SIO_MODULE: ftdi_module_detach_mode = 0;
 pub const ftdi_module_detach_mode_DONT_DETACH_SIO_MODULE: ftdi_module_detach_mode = 1;
+pub const ftdi_module_detach_mode_AUTO_DETACH_REATACH_SIO_MODULE: ftdi_module_detach_mode = 2;
 pub type ftdi_module_detach_mode = u32;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -921,6 +1172,15 @@ extern "C" {
 extern "C" {
     pub fn ftdi_usb_purge_buffers(ftdi: *mut ftdi_context) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn ftdi_tciflush(ftdi: *mut ftdi_context) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ftdi_tcoflush(ftdi: *mut ftdi_context) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn ftdi_tcioflush(ftdi: *mut ftdi_context) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn ftdi_set_baudrate(
         ftdi: *mut ftdi_context,
@@ -1051,6 +1311,13 @@ extern "C" {
         flowctrl: ::std::os::raw::c_int,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn ftdi_setflowctrl_xonxoff(
+        ftdi: *mut ftdi_context,
+        xon: ::std::os::raw::c_uchar,
+        xoff: ::std::os::raw::c_uchar,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn ftdi_setdtr_rts(
         ftdi: *mut ftdi_context,